@@ -1,68 +1,343 @@
-fn part1(input: &str) -> Result<usize, String> {
-    input
-        .lines()
-        .map(|line| {
-            let mut charstack: Vec<char> = Vec::new();
-            for c in line.chars() {
-                match c {
-                    '[' => charstack.push(']'),
-                    '(' => charstack.push(')'),
-                    '{' => charstack.push('}'),
-                    '<' => charstack.push('>'),
-                    _ => {
-                        if charstack.pop() != Some(c) {
-                            match c {
-                                ')' => return Ok(3),
-                                ']' => return Ok(57),
-                                '}' => return Ok(1197),
-                                '>' => return Ok(25137),
-                                _ => return Err(format!("Unexpected character {}", c)),
-                            }
-                        }
-                    }
+use std::collections::HashMap;
+use std::fmt::Display;
+
+type EType = String;
+
+/// A bracket pair and what its closing character scores in each part.
+struct Pair {
+    open: char,
+    close: char,
+    /// Score for a line corrupted by this closing character.
+    corrupted: usize,
+    /// Digit for this closing character in a completion score.
+    completion: usize,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Report {
+    Valid,
+    /// The first bad closing character, at a 1-based `column`. `expected` is
+    /// `None` when nothing was open.
+    Corrupted {
+        column: usize,
+        expected: Option<char>,
+        found: char,
+    },
+    /// The closing characters which would finish the line.
+    Incomplete {
+        completion: String,
+    },
+}
+
+/// A single-character change to a line, at a 1-based column of the original
+/// line. An insertion goes before its column.
+#[derive(Debug, PartialEq, Eq)]
+enum Edit {
+    Insert { column: usize, c: char },
+    Delete { column: usize, c: char },
+    Substitute { column: usize, from: char, to: char },
+}
+
+impl Edit {
+    fn column(&self) -> usize {
+        match self {
+            Edit::Insert { column, .. }
+            | Edit::Delete { column, .. }
+            | Edit::Substitute { column, .. } => *column,
+        }
+    }
+}
+
+impl Display for Edit {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Edit::Insert { column, c } => write!(f, "column {}: insert {}", column, c),
+            Edit::Delete { column, c } => write!(f, "column {}: delete {}", column, c),
+            Edit::Substitute { column, from, to } => {
+                write!(f, "column {}: {} -> {}", column, from, to)
+            }
+        }
+    }
+}
+
+/// How the cheapest repair of a span treats its first character.
+#[derive(Clone, Copy)]
+enum Choice {
+    Empty,
+    /// Closed by an insertion if it opens, deleted if it closes.
+    Unmatched,
+    /// Paired with the character at this index.
+    Match(usize),
+}
+
+/// Applies edits sorted as `SyntaxChecker::repair` returns them.
+fn apply_edits(line: &str, edits: &[Edit]) -> String {
+    let mut edits = edits.iter().peekable();
+    let mut out = String::new();
+    for (i, c) in line.chars().chain(std::iter::once('\0')).enumerate() {
+        let mut keep = c != '\0';
+        while let Some(edit) = edits.next_if(|edit| edit.column() == i + 1) {
+            match edit {
+                Edit::Insert { c, .. } => out.push(*c),
+                Edit::Delete { .. } => keep = false,
+                Edit::Substitute { to, .. } => {
+                    out.push(*to);
+                    keep = false;
                 }
             }
-            return Ok(0);
-        })
-        .sum()
+        }
+        if keep {
+            out.push(c);
+        }
+    }
+    out
 }
 
-// 193275 correct
+struct SyntaxChecker {
+    pairs: Vec<Pair>,
+    /// Closing character for each opening character.
+    closers: HashMap<char, char>,
+    /// Index into `pairs` for each closing character.
+    by_close: HashMap<char, usize>,
+}
 
-fn part2(input: &str) -> Result<usize, String> {
-    let mut scores: Vec<usize> = input
-        .lines()
-        .filter_map(|line| {
-            let mut charstack: Vec<char> = Vec::new();
-            for c in line.chars() {
-                match c {
-                    '[' => charstack.push(']'),
-                    '(' => charstack.push(')'),
-                    '{' => charstack.push('}'),
-                    '<' => charstack.push('>'),
-                    _ => {
-                        if charstack.pop() != Some(c) {
-                            match c {
-                                ')' | ']' | '}' | '>' => return None,
-                                _ => return Some(Err(format!("Unexpected character {}", c))),
-                            }
-                        }
+impl SyntaxChecker {
+    fn new(pairs: Vec<Pair>) -> Result<Self, EType> {
+        let mut closers = HashMap::new();
+        let mut by_close = HashMap::new();
+        for (i, pair) in pairs.iter().enumerate() {
+            if pair.open == pair.close
+                || closers.contains_key(&pair.close)
+                || by_close.contains_key(&pair.open)
+            {
+                return Err(format!(
+                    "{} and {} are used as both open and close",
+                    pair.open, pair.close
+                ));
+            }
+            if closers.insert(pair.open, pair.close).is_some() {
+                return Err(format!("Duplicate opening character {}", pair.open));
+            }
+            if by_close.insert(pair.close, i).is_some() {
+                return Err(format!("Duplicate closing character {}", pair.close));
+            }
+        }
+        Ok(SyntaxChecker {
+            pairs,
+            closers,
+            by_close,
+        })
+    }
+
+    /// The puzzle's four bracket pairs.
+    fn puzzle() -> Self {
+        let pairs = [
+            ('(', ')', 3, 1),
+            ('[', ']', 57, 2),
+            ('{', '}', 1197, 3),
+            ('<', '>', 25137, 4),
+        ]
+        .into_iter()
+        .map(|(open, close, corrupted, completion)| Pair {
+            open,
+            close,
+            corrupted,
+            completion,
+        })
+        .collect();
+        Self::new(pairs).unwrap()
+    }
+
+    fn check(&self, line: &str) -> Result<Report, EType> {
+        let mut expected: Vec<char> = Vec::new();
+        for (i, c) in line.chars().enumerate() {
+            if let Some(&close) = self.closers.get(&c) {
+                expected.push(close);
+            } else if self.by_close.contains_key(&c) {
+                let open = expected.pop();
+                if open != Some(c) {
+                    return Ok(Report::Corrupted {
+                        column: i + 1,
+                        expected: open,
+                        found: c,
+                    });
+                }
+            } else {
+                return Err(format!("Unexpected character {} at column {}", c, i + 1));
+            }
+        }
+        Ok(if expected.is_empty() {
+            Report::Valid
+        } else {
+            Report::Incomplete {
+                completion: expected.into_iter().rev().collect(),
+            }
+        })
+    }
+
+    fn check_all(&self, input: &str) -> Result<Vec<Report>, EType> {
+        input.lines().map(|line| self.check(line)).collect()
+    }
+
+    fn is_open(&self, c: char) -> bool {
+        self.closers.contains_key(&c)
+    }
+
+    fn pair(&self, close: char) -> &Pair {
+        &self.pairs[self.by_close[&close]]
+    }
+
+    /// The corruption score of a report, zero unless it is corrupted.
+    fn corrupted_score(&self, report: &Report) -> usize {
+        match report {
+            Report::Corrupted { found, .. } => self.pair(*found).corrupted,
+            _ => 0,
+        }
+    }
+
+    /// The completion score of a report, if it is incomplete. Each closing
+    /// character is a digit in base one more than the number of pairs.
+    fn completion_score(&self, report: &Report) -> Option<usize> {
+        let base = self.pairs.len() + 1;
+        match report {
+            Report::Incomplete { completion } => Some(
+                completion
+                    .chars()
+                    .fold(0, |acc, c| base * acc + self.pair(c).completion),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Substitutions making `a` open a pair closed by `b`.
+    fn pair_edits(&self, a: (usize, char), b: (usize, char)) -> Vec<Edit> {
+        let substitute = |(i, from): (usize, char), to: char| Edit::Substitute {
+            column: i + 1,
+            from,
+            to,
+        };
+        match (self.is_open(a.1), self.is_open(b.1)) {
+            (true, _) if self.closers[&a.1] == b.1 => vec![],
+            (true, _) => vec![substitute(b, self.closers[&a.1])],
+            (false, false) => vec![substitute(a, self.pair(b.1).open)],
+            (false, true) => {
+                let pair = &self.pairs[0];
+                vec![substitute(a, pair.open), substitute(b, pair.close)]
+            }
+        }
+    }
+
+    /// The fewest insertions, deletions and substitutions which make a line
+    /// valid, sorted by column. An unclosed pair is closed rather than
+    /// deleted, and a stray closing character is deleted.
+    fn repair(&self, line: &str) -> Result<Vec<Edit>, EType> {
+        let chars: Vec<char> = line.chars().collect();
+        if let Some((i, c)) = chars
+            .iter()
+            .enumerate()
+            .find(|(_, c)| !self.closers.contains_key(c) && !self.by_close.contains_key(c))
+        {
+            return Err(format!("Unexpected character {} at column {}", c, i + 1));
+        }
+
+        // cost[i][j] is the cheapest repair of chars[i..j]
+        let n = chars.len();
+        let mut cost = vec![vec![0; n + 1]; n + 1];
+        let mut choice = vec![vec![Choice::Empty; n + 1]; n + 1];
+        for len in 1..=n {
+            for i in 0..=n - len {
+                let j = i + len;
+                let mut best = (1 + cost[i + 1][j], Choice::Unmatched);
+                for k in i + 1..j {
+                    let pair = self.pair_edits((i, chars[i]), (k, chars[k])).len();
+                    let total = pair + cost[i + 1][k] + cost[k + 1][j];
+                    if total < best.0 {
+                        best = (total, Choice::Match(k));
                     }
                 }
+                (cost[i][j], choice[i][j]) = best;
             }
+        }
 
-            Some(charstack.into_iter().rev().fold(Ok(0usize), |racc, c| {
-                racc.and_then(|acc| match c {
-                    ')' => Ok(5 * acc + 1),
-                    ']' => Ok(5 * acc + 2),
-                    '}' => Ok(5 * acc + 3),
-                    '>' => Ok(5 * acc + 4),
-                    _ => Err(format!("Unexpected character {}", c)),
-                })
-            }))
-        })
-        .collect::<Result<Vec<usize>, String>>()?;
+        let mut edits = Vec::new();
+        let mut spans = vec![(0, n)];
+        while let Some((i, j)) = spans.pop() {
+            match choice[i][j] {
+                Choice::Empty => {}
+                Choice::Unmatched if self.is_open(chars[i]) => {
+                    edits.push(Edit::Insert {
+                        column: j + 1,
+                        c: self.closers[&chars[i]],
+                    });
+                    spans.push((i + 1, j));
+                }
+                Choice::Unmatched => {
+                    edits.push(Edit::Delete {
+                        column: i + 1,
+                        c: chars[i],
+                    });
+                    spans.push((i + 1, j));
+                }
+                Choice::Match(k) => {
+                    edits.extend(self.pair_edits((i, chars[i]), (k, chars[k])));
+                    spans.push((i + 1, k));
+                    spans.push((k + 1, j));
+                }
+            }
+        }
+        // Outer spans were visited first, so reversing puts inner insertions
+        // before outer ones at the same column
+        edits.reverse();
+        edits.sort_by_key(|edit| (edit.column(), !matches!(edit, Edit::Insert { .. })));
+        Ok(edits)
+    }
+}
+
+/// Repairs every line, returning the new text and a diff-like summary of
+/// the changed lines.
+fn repair_text(checker: &SyntaxChecker, input: &str) -> Result<(String, String), EType> {
+    let mut text = String::new();
+    let mut summary = String::new();
+    let (mut changed, mut total) = (0, 0);
+    for (n, line) in input.lines().enumerate() {
+        let edits = checker.repair(line)?;
+        let repaired = apply_edits(line, &edits);
+        if !edits.is_empty() {
+            summary += &format!("line {}:\n- {}\n+ {}\n", n + 1, line, repaired);
+            for edit in &edits {
+                summary += &format!("    {}\n", edit);
+            }
+            changed += 1;
+            total += edits.len();
+        }
+        text += &repaired;
+        text.push('\n');
+    }
+    summary += &format!("Repaired {} lines with {} edits\n", changed, total);
+    Ok((text, summary))
+}
 
+fn part1(input: &str) -> Result<usize, EType> {
+    let checker = SyntaxChecker::puzzle();
+    Ok(checker
+        .check_all(input)?
+        .iter()
+        .map(|report| checker.corrupted_score(report))
+        .sum())
+}
+
+// 193275 correct
+
+fn part2(input: &str) -> Result<usize, EType> {
+    let checker = SyntaxChecker::puzzle();
+    let mut scores: Vec<usize> = checker
+        .check_all(input)?
+        .iter()
+        .filter_map(|report| checker.completion_score(report))
+        .collect();
+    if scores.is_empty() {
+        return Err("No incomplete lines".to_string());
+    }
     scores.sort_unstable();
     Ok(scores[scores.len() >> 1])
 }
@@ -75,4 +350,202 @@ fn main() {
     let input = include_str!("../input.txt");
     println!("Part 1: {:?}", part1(input));
     println!("Part 2: {:?}", part2(input));
+
+    // "repair <file> [output]" rewrites a file with every line made valid
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() > 2 && args[1] == "repair" {
+        let output = args.get(3).unwrap_or(&args[2]);
+        let input = std::fs::read_to_string(&args[2]).expect("Failed to read input file");
+        match repair_text(&SyntaxChecker::puzzle(), &input) {
+            Ok((text, summary)) => {
+                print!("{}", summary);
+                std::fs::write(output, text).expect("Failed to write output file");
+            }
+            Err(e) => println!("Repair failed: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_example() {
+        let input = include_str!("../example1.txt");
+        assert_eq!(part1(input), Ok(26397));
+        assert_eq!(part2(input), Ok(288957));
+    }
+
+    #[test]
+    fn test_reports() {
+        let checker = SyntaxChecker::puzzle();
+        assert_eq!(
+            checker.check("{([(<{}[<>[]}>{[]{[(<()>"),
+            Ok(Report::Corrupted {
+                column: 13,
+                expected: Some(']'),
+                found: '}'
+            })
+        );
+        assert_eq!(
+            checker.check("<{([{{}}[<[[[<>{}]]]>[]]"),
+            Ok(Report::Incomplete {
+                completion: "])}>".to_string()
+            })
+        );
+        assert_eq!(
+            checker.check(")"),
+            Ok(Report::Corrupted {
+                column: 1,
+                expected: None,
+                found: ')'
+            })
+        );
+        assert_eq!(checker.check("([]<>)"), Ok(Report::Valid));
+        assert!(checker.check("(x)").is_err());
+    }
+
+    #[test]
+    fn test_custom_pairs() {
+        let pairs = vec![
+            Pair {
+                open: '/',
+                close: '\\',
+                corrupted: 10,
+                completion: 1,
+            },
+            Pair {
+                open: 'a',
+                close: 'b',
+                corrupted: 20,
+                completion: 2,
+            },
+        ];
+        let checker = SyntaxChecker::new(pairs).unwrap();
+        let report = checker.check("/a/").unwrap();
+        assert_eq!(
+            report,
+            Report::Incomplete {
+                completion: "\\b\\".to_string()
+            }
+        );
+        assert_eq!(checker.completion_score(&report), Some(9 + 2 * 3 + 1));
+        assert_eq!(checker.corrupted_score(&checker.check("/b").unwrap()), 20);
+
+        let clash = vec![
+            Pair {
+                open: '(',
+                close: ')',
+                corrupted: 1,
+                completion: 1,
+            },
+            Pair {
+                open: ')',
+                close: '(',
+                corrupted: 1,
+                completion: 1,
+            },
+        ];
+        assert!(SyntaxChecker::new(clash).is_err());
+        let same = vec![Pair {
+            open: '|',
+            close: '|',
+            corrupted: 1,
+            completion: 1,
+        }];
+        assert!(SyntaxChecker::new(same).is_err());
+    }
+
+    #[test]
+    fn test_repair() {
+        let checker = SyntaxChecker::puzzle();
+        assert_eq!(
+            checker.repair("(]"),
+            Ok(vec![Edit::Substitute {
+                column: 2,
+                from: ']',
+                to: ')'
+            }])
+        );
+        assert_eq!(
+            checker.repair("())"),
+            Ok(vec![Edit::Delete { column: 3, c: ')' }])
+        );
+        let edits = checker.repair("<[()").unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(apply_edits("<[()", &edits), "<>()");
+        assert!(checker.repair("(x").is_err());
+
+        let (text, summary) = repair_text(&checker, include_str!("../example1.txt")).unwrap();
+        assert!(text
+            .lines()
+            .all(|line| checker.check(line) == Ok(Report::Valid)));
+        assert!(summary.starts_with("line 1:\n- [({(<(())[]>[[{[]{<()<>>\n"));
+    }
+
+    #[test]
+    fn test_repair_is_minimal() {
+        // Breadth-first search out from every valid string over two pairs
+        let checker = SyntaxChecker::puzzle();
+        let alphabet = ['(', ')', '[', ']'];
+        let mut strings = vec![String::new()];
+        for len in 1..=6 {
+            let shorter: Vec<String> = strings
+                .iter()
+                .filter(|s| s.len() == len - 1)
+                .cloned()
+                .collect();
+            for s in shorter {
+                strings.extend(alphabet.iter().map(|&c| format!("{}{}", s, c)));
+            }
+        }
+        let mut dist: HashMap<String, usize> = HashMap::new();
+        let mut queue = std::collections::VecDeque::new();
+        for s in &strings {
+            if checker.check(s) == Ok(Report::Valid) {
+                dist.insert(s.clone(), 0);
+                queue.push_back(s.clone());
+            }
+        }
+        while let Some(s) = queue.pop_front() {
+            let chars: Vec<char> = s.chars().collect();
+            let mut next = Vec::new();
+            for i in 0..=chars.len() {
+                for &c in &alphabet {
+                    let mut v = chars.clone();
+                    v.insert(i, c);
+                    next.push(v);
+                    if i < chars.len() {
+                        let mut v = chars.clone();
+                        v[i] = c;
+                        next.push(v);
+                    }
+                }
+                if i < chars.len() {
+                    let mut v = chars.clone();
+                    v.remove(i);
+                    next.push(v);
+                }
+            }
+            for v in next {
+                let t: String = v.into_iter().collect();
+                if t.len() <= 6 && !dist.contains_key(&t) {
+                    dist.insert(t.clone(), dist[&s] + 1);
+                    queue.push_back(t);
+                }
+            }
+        }
+
+        for s in strings.iter().filter(|s| s.len() <= 3) {
+            let edits = checker.repair(s).unwrap();
+            assert_eq!(edits.len(), dist[s], "{}", s);
+            assert_eq!(
+                checker.check(&apply_edits(s, &edits)),
+                Ok(Report::Valid),
+                "{}",
+                s
+            );
+        }
+    }
 }
@@ -1,125 +1,369 @@
-fn part1(mut grid: Vec<Vec<u8>>) -> usize {
-    let mut total_flash_count: usize = 0;
-
-    for _ in 0..100 {
-        let mut check_stack = (0..grid.len())
-            .flat_map(|y| (0..grid[y].len()).map(move |x| (x, y)))
-            .collect::<Vec<(usize, usize)>>();
-
-        let mut flashes: Vec<(usize, usize)> = Vec::new();
-
-        while let Some((x, y)) = check_stack.pop() {
-            grid[y][x] += 1;
-            if grid[y][x] > 9 {
-                grid[y][x] = 0;
-                flashes.push((x, y));
-
-                if y > 0 {
-                    check_stack.push((x, y - 1));
-                    if x > 0 {
-                        check_stack.push((x - 1, y - 1));
-                    }
-                    if x < grid[y].len() - 1 {
-                        check_stack.push((x + 1, y - 1));
-                    }
-                }
-                if y < grid.len() - 1 {
-                    check_stack.push((x, y + 1));
-                    if x > 0 {
-                        check_stack.push((x - 1, y + 1));
-                    }
-                    if x < grid[y].len() - 1 {
-                        check_stack.push((x + 1, y + 1));
-                    }
-                }
-                if x > 0 {
-                    check_stack.push((x - 1, y));
-                }
-                if x < grid[y].len() - 1 {
-                    check_stack.push((x + 1, y));
-                }
-            }
-        }
+use std::collections::HashMap;
 
-        total_flash_count += flashes.len();
+type Pos = (usize, usize);
+type Energy = u32;
 
-        flashes.into_iter().for_each(|(x, y)| {
-            grid[y][x] = 0;
-        });
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Neighbourhood {
+    /// The four orthogonal neighbours.
+    VonNeumann,
+    /// The eight surrounding octopuses.
+    Moore,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Edges {
+    /// Octopuses on the edge have fewer neighbours.
+    Clamped,
+    /// The grid wraps around like a torus.
+    Toroidal,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Rules {
+    /// An octopus flashes once its energy exceeds this.
+    threshold: Energy,
+    /// Energy every octopus gains at the start of a step.
+    gain: Energy,
+    /// Energy a flash gives each neighbour.
+    flash_energy: Energy,
+    neighbourhood: Neighbourhood,
+    edges: Edges,
+}
+
+impl Default for Rules {
+    /// The puzzle's rules.
+    fn default() -> Self {
+        Rules {
+            threshold: 9,
+            gain: 1,
+            flash_energy: 1,
+            neighbourhood: Neighbourhood::Moore,
+            edges: Edges::Clamped,
+        }
     }
+}
 
-    total_flash_count
+/// A grid which returned to an earlier state without ever synchronising,
+/// so never will.
+#[derive(Debug, PartialEq, Eq)]
+struct Cycle {
+    /// The step after which the repeating states begin.
+    start: usize,
+    period: usize,
 }
 
-// 1665 correct
+/// What happened during one step.
+#[derive(Debug, PartialEq, Eq)]
+struct StepReport {
+    /// Every octopus which flashed as (x, y), in the order the cascade
+    /// reached them.
+    flashes: Vec<Pos>,
+    /// How many rounds of flashing the cascade took.
+    rounds: usize,
+}
 
-fn part2(mut grid: Vec<Vec<u8>>) -> usize {
-    let mut step: usize = 0;
+struct OctopusGrid {
+    energy: Vec<Vec<Energy>>,
+    rules: Rules,
+    /// Steps taken so far.
+    steps: usize,
+}
 
-    let mut check_stack: Vec<(usize, usize)>;
-    let mut flashes: Vec<(usize, usize)> = Vec::new();
+impl OctopusGrid {
+    fn parse(input: &str) -> Result<Self, String> {
+        Self::with_rules(input, Rules::default())
+    }
 
-    loop {
-        step += 1;
-        check_stack = (0..grid.len())
-            .flat_map(|y| (0..grid[y].len()).map(move |x| (x, y)))
-            .collect::<Vec<(usize, usize)>>();
+    fn with_rules(input: &str, rules: Rules) -> Result<Self, String> {
+        let energy = input
+            .lines()
+            .map(|l| {
+                l.chars()
+                    .map(|c| match c.to_digit(10) {
+                        Some(d) => Ok(d),
+                        None => Err(format!("Bad energy level {}", c)),
+                    })
+                    .collect::<Result<Vec<Energy>, String>>()
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        if energy.is_empty() || energy.iter().any(|row| row.len() != energy[0].len()) {
+            return Err("Grid must be a non-empty rectangle".to_string());
+        }
+        Ok(OctopusGrid {
+            energy,
+            rules,
+            steps: 0,
+        })
+    }
 
-        flashes.clear();
+    fn len(&self) -> usize {
+        self.energy.len() * self.energy[0].len()
+    }
 
-        while let Some((x, y)) = check_stack.pop() {
-            grid[y][x] += 1;
-            if grid[y][x] > 9 {
-                grid[y][x] = 0;
-                flashes.push((x, y));
+    fn neighbours(&self, (x, y): Pos) -> Vec<Pos> {
+        let (height, width) = (self.energy.len() as isize, self.energy[0].len() as isize);
+        let offsets: &[(isize, isize)] = match self.rules.neighbourhood {
+            Neighbourhood::VonNeumann => &[(0, -1), (-1, 0), (1, 0), (0, 1)],
+            Neighbourhood::Moore => &[
+                (-1, -1),
+                (0, -1),
+                (1, -1),
+                (-1, 0),
+                (1, 0),
+                (-1, 1),
+                (0, 1),
+                (1, 1),
+            ],
+        };
+        let mut neighbours: Vec<Pos> = offsets
+            .iter()
+            .map(|&(dx, dy)| (x as isize + dx, y as isize + dy))
+            .filter_map(|(nx, ny)| match self.rules.edges {
+                Edges::Clamped => {
+                    ((0..width).contains(&nx) && (0..height).contains(&ny)).then_some((nx, ny))
+                }
+                Edges::Toroidal => Some((nx.rem_euclid(width), ny.rem_euclid(height))),
+            })
+            .map(|(nx, ny)| (nx as usize, ny as usize))
+            .filter(|&n| n != (x, y))
+            .collect();
+        // A narrow torus reaches some neighbours from both sides
+        neighbours.sort_unstable();
+        neighbours.dedup();
+        neighbours
+    }
 
-                if y > 0 {
-                    check_stack.push((x, y - 1));
-                    if x > 0 {
-                        check_stack.push((x - 1, y - 1));
-                    }
-                    if x < grid[y].len() - 1 {
-                        check_stack.push((x + 1, y - 1));
-                    }
+    fn step(&mut self) -> StepReport {
+        let mut flashes = Vec::new();
+        let mut round = Vec::new();
+        for (y, row) in self.energy.iter_mut().enumerate() {
+            for (x, e) in row.iter_mut().enumerate() {
+                *e += self.rules.gain;
+                if *e > self.rules.threshold {
+                    round.push((x, y));
                 }
-                if y < grid.len() - 1 {
-                    check_stack.push((x, y + 1));
-                    if x > 0 {
-                        check_stack.push((x - 1, y + 1));
-                    }
-                    if x < grid[y].len() - 1 {
-                        check_stack.push((x + 1, y + 1));
+            }
+        }
+
+        // Each round's flashes push their neighbours over to make the next
+        let mut rounds = 0;
+        while !round.is_empty() {
+            rounds += 1;
+            let mut next = Vec::new();
+            for &pos in &round {
+                for (nx, ny) in self.neighbours(pos) {
+                    let e = &mut self.energy[ny][nx];
+                    let before = *e;
+                    *e += self.rules.flash_energy;
+                    if before <= self.rules.threshold && *e > self.rules.threshold {
+                        next.push((nx, ny));
                     }
                 }
-                if x > 0 {
-                    check_stack.push((x - 1, y));
-                }
-                if x < grid[y].len() - 1 {
-                    check_stack.push((x + 1, y));
-                }
             }
+            flashes.append(&mut round);
+            round = next;
+        }
+
+        for &(x, y) in &flashes {
+            self.energy[y][x] = 0;
         }
+        self.steps += 1;
+        StepReport { flashes, rounds }
+    }
+
+    fn run(&mut self, n: usize) -> Vec<StepReport> {
+        (0..n).map(|_| self.step()).collect()
+    }
 
-        if flashes.len() == grid.len() * grid[0].len() {
-            break;
+    /// Steps until every octopus flashes at once, returning that step's
+    /// number. Every octopus ends a step at or below the threshold, so there
+    /// are finitely many states and a grid which never synchronises repeats.
+    fn run_until_synchronised(&mut self) -> Result<usize, Cycle> {
+        let mut seen: HashMap<Vec<Vec<Energy>>, usize> = HashMap::new();
+        loop {
+            if let Some(start) = seen.insert(self.energy.clone(), self.steps) {
+                return Err(Cycle {
+                    start,
+                    period: self.steps - start,
+                });
+            }
+            if self.step().flashes.len() == self.len() {
+                return Ok(self.steps);
+            }
         }
+    }
+}
 
-        flashes.iter().for_each(|&(x, y)| {
-            grid[y][x] = 0;
-        });
+/// The octopus which flashed most often across the reports, and how often.
+/// Ties go to the first in reading order.
+fn most_flashes(reports: &[StepReport]) -> Option<(Pos, usize)> {
+    let mut counts: HashMap<Pos, usize> = HashMap::new();
+    for &pos in reports.iter().flat_map(|r| &r.flashes) {
+        *counts.entry(pos).or_default() += 1;
     }
+    counts
+        .into_iter()
+        .max_by_key(|&((x, y), n)| (n, std::cmp::Reverse((y, x))))
+}
 
-    step
+fn part1(mut grid: OctopusGrid) -> usize {
+    grid.run(100).iter().map(|r| r.flashes.len()).sum()
+}
+
+// 1665 correct
+
+fn part2(mut grid: OctopusGrid) -> Result<usize, Cycle> {
+    grid.run_until_synchronised()
 }
 
 // 235 correct
 
 fn main() {
-    let input = include_str!("../input.txt")
-        .lines()
-        .map(|l| l.chars().map(|c| c as u8 - '0' as u8).collect::<Vec<u8>>())
-        .collect::<Vec<_>>();
+    let input = include_str!("../input.txt");
 
-    println!("Part 1: {}", part1(input.clone()));
-    println!("Part 2: {}", part2(input));
+    println!("Part 1: {}", part1(OctopusGrid::parse(input).unwrap()));
+    match part2(OctopusGrid::parse(input).unwrap()) {
+        Ok(step) => println!("Part 2: {}", step),
+        Err(cycle) => println!("Part 2: never synchronises ({:?})", cycle),
+    }
+
+    let reports = OctopusGrid::parse(input).unwrap().run(100);
+    if let Some(((x, y), n)) = most_flashes(&reports) {
+        println!("Most flashes in 100 steps: {} at ({}, {})", n, x, y);
+    }
+
+    // Optional rules as "threshold=N", "gain=N", "flash=N", "vonneumann"
+    // and "toroidal"
+    let mut rules = Rules::default();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    for arg in &args {
+        match arg.split_once('=') {
+            Some(("threshold", n)) => rules.threshold = n.parse().unwrap(),
+            Some(("gain", n)) => rules.gain = n.parse().unwrap(),
+            Some(("flash", n)) => rules.flash_energy = n.parse().unwrap(),
+            _ if arg == "vonneumann" => rules.neighbourhood = Neighbourhood::VonNeumann,
+            _ if arg == "toroidal" => rules.edges = Edges::Toroidal,
+            _ => panic!("Unknown rule {}", arg),
+        }
+    }
+    if !args.is_empty() {
+        let mut grid = OctopusGrid::with_rules(input, rules).unwrap();
+        let flashes: usize = grid.run(100).iter().map(|r| r.flashes.len()).sum();
+        println!("Flashes in 100 steps: {}", flashes);
+        match OctopusGrid::with_rules(input, rules)
+            .unwrap()
+            .run_until_synchronised()
+        {
+            Ok(step) => println!("Synchronised at step {}", step),
+            Err(cycle) => println!(
+                "Never synchronises: repeats every {} steps from step {}",
+                cycle.period, cycle.start
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_example() {
+        let input = include_str!("../example1.txt");
+        assert_eq!(part1(OctopusGrid::parse(input).unwrap()), 1656);
+        assert_eq!(part2(OctopusGrid::parse(input).unwrap()), Ok(195));
+    }
+
+    #[test]
+    fn test_cascade() {
+        let mut grid = OctopusGrid::parse("11111\n19991\n19191\n19991\n11111").unwrap();
+        let report = grid.step();
+        assert_eq!(report.rounds, 2);
+        assert_eq!(report.flashes.len(), 9);
+        assert_eq!(report.flashes[8], (2, 2));
+        assert_eq!(grid.energy[0], vec![3, 4, 5, 4, 3]);
+        assert_eq!(grid.energy[2], vec![5, 0, 0, 0, 5]);
+
+        let report = grid.step();
+        assert_eq!(
+            report,
+            StepReport {
+                flashes: vec![],
+                rounds: 0
+            }
+        );
+        assert_eq!(grid.steps, 2);
+    }
+
+    #[test]
+    fn test_most_flashes() {
+        let mut grid = OctopusGrid::parse("11111\n19991\n19191\n19991\n11111").unwrap();
+        assert_eq!(most_flashes(&grid.run(1)), Some(((1, 1), 1)));
+        assert_eq!(most_flashes(&[]), None);
+        assert!(OctopusGrid::parse("12\n3").is_err());
+    }
+
+    #[test]
+    fn test_neighbourhoods() {
+        let rules = |neighbourhood, edges| Rules {
+            neighbourhood,
+            edges,
+            ..Default::default()
+        };
+        let grid = |rules| OctopusGrid::with_rules("000\n000\n000\n000", rules).unwrap();
+        let moore = grid(rules(Neighbourhood::Moore, Edges::Clamped));
+        assert_eq!(moore.neighbours((0, 0)), vec![(0, 1), (1, 0), (1, 1)]);
+        let torus = grid(rules(Neighbourhood::VonNeumann, Edges::Toroidal));
+        assert_eq!(
+            torus.neighbours((0, 0)),
+            vec![(0, 1), (0, 3), (1, 0), (2, 0)]
+        );
+        let narrow = OctopusGrid::with_rules("00\n00", torus.rules).unwrap();
+        assert_eq!(narrow.neighbours((0, 0)), vec![(0, 1), (1, 0)]);
+    }
+
+    #[test]
+    fn test_rules() {
+        // Two units per flash carry a cascade across gaps of one
+        let rules = Rules {
+            threshold: 5,
+            gain: 2,
+            flash_energy: 2,
+            neighbourhood: Neighbourhood::VonNeumann,
+            ..Default::default()
+        };
+        let mut grid = OctopusGrid::with_rules("4222", rules).unwrap();
+        let report = grid.step();
+        assert_eq!(report.flashes, vec![(0, 0), (1, 0), (2, 0), (3, 0)]);
+        assert_eq!(report.rounds, 4);
+        assert_eq!(grid.energy, vec![vec![0; 4]]);
+        assert_eq!(grid.run_until_synchronised(), Ok(4));
+    }
+
+    #[test]
+    fn test_never_synchronises() {
+        // Without gain nothing ever changes
+        let rules = Rules {
+            gain: 0,
+            ..Default::default()
+        };
+        let mut grid = OctopusGrid::with_rules("123\n456", rules).unwrap();
+        assert_eq!(
+            grid.run_until_synchronised(),
+            Err(Cycle {
+                start: 0,
+                period: 1
+            })
+        );
+
+        // A lone clamped flasher never drags its far neighbour along
+        let rules = Rules {
+            neighbourhood: Neighbourhood::VonNeumann,
+            ..Default::default()
+        };
+        let mut grid = OctopusGrid::with_rules("900", rules).unwrap();
+        let cycle = grid.run_until_synchronised().unwrap_err();
+        assert_eq!(cycle.period, 9);
+    }
 }
@@ -1,4 +1,8 @@
-use std::{collections::BTreeSet, str::FromStr};
+use std::{
+    collections::{BTreeSet, HashMap, VecDeque},
+    hash::Hash,
+    str::FromStr,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum CaveCellType {
@@ -9,6 +13,8 @@ enum CaveCellType {
 struct CaveCell {
     cavename: String,
     cavetype: CaveCellType,
+    /// Position among the small caves, for sets of them as bitmasks.
+    small_index: Option<usize>,
     links: BTreeSet<usize>,
 }
 
@@ -22,37 +28,44 @@ impl Eq for CaveCell {}
 
 struct CaveGraph {
     cells: Vec<CaveCell>,
+    ids: HashMap<String, CaveId>,
+    small_caves: usize,
 }
 
 type CaveId = usize;
 
 impl CaveGraph {
     fn new() -> CaveGraph {
-        CaveGraph { cells: Vec::new() }
+        CaveGraph {
+            cells: Vec::new(),
+            ids: HashMap::new(),
+            small_caves: 0,
+        }
     }
 
     fn get_cell(&self, cavename: &str) -> Option<CaveId> {
-        self.cells.iter().position(|cell| cell.cavename == cavename)
-    }
-
-    fn get_cell_data(&self, caveid: CaveId) -> Option<&CaveCell> {
-        self.cells.get(caveid)
+        self.ids.get(cavename).copied()
     }
 
     fn get_or_create_cell(&mut self, cavename: &str) -> CaveId {
-        if let Some(i) = self.cells.iter().position(|cell| cell.cavename == cavename) {
+        if let Some(i) = self.get_cell(cavename) {
             i
         } else {
+            let cavetype = if cavename.chars().all(|c| c.is_uppercase()) {
+                CaveCellType::BigCave
+            } else {
+                CaveCellType::SmallCave
+            };
+            let small_index = (cavetype == CaveCellType::SmallCave).then_some(self.small_caves);
+            self.small_caves += small_index.is_some() as usize;
             let cell = CaveCell {
                 cavename: cavename.to_string(),
-                cavetype: if cavename.chars().all(|c| c.is_uppercase()) {
-                    CaveCellType::BigCave
-                } else {
-                    CaveCellType::SmallCave
-                },
+                cavetype,
+                small_index,
                 links: BTreeSet::new(),
             };
             self.cells.push(cell);
+            self.ids.insert(cavename.to_string(), self.cells.len() - 1);
             self.cells.len() - 1
         }
     }
@@ -63,6 +76,28 @@ impl CaveGraph {
         self.cells[a].links.insert(b);
         self.cells[b].links.insert(a);
     }
+
+    /// The graph in Graphviz DOT, with big caves as filled boxes and small
+    /// caves as ellipses.
+    fn to_dot(&self) -> String {
+        let mut dot = "graph caves {\n".to_string();
+        for cell in &self.cells {
+            let style = match cell.cavetype {
+                CaveCellType::BigCave => "shape=box, style=filled",
+                CaveCellType::SmallCave => "shape=ellipse",
+            };
+            dot += &format!("    \"{}\" [{}];\n", cell.cavename, style);
+        }
+        for (a, cell) in self.cells.iter().enumerate() {
+            for &b in cell.links.range(a + 1..) {
+                dot += &format!(
+                    "    \"{}\" -- \"{}\";\n",
+                    cell.cavename, self.cells[b].cavename
+                );
+            }
+        }
+        dot + "}\n"
+    }
 }
 
 impl FromStr for CaveGraph {
@@ -81,98 +116,327 @@ impl FromStr for CaveGraph {
     }
 }
 
-fn part1(graph: &CaveGraph) -> usize {
-    let start = graph.get_cell("start").unwrap();
-    let end = graph.get_cell("end").unwrap();
+/// Decides which caves a path may enter next. The state must capture all
+/// the policy needs to know about the path so far, as paths are counted by
+/// memoising over (cave, state).
+trait VisitPolicy {
+    type State: Clone + Eq + Hash;
 
-    let mut unique_paths = 0;
+    fn initial(&self, graph: &CaveGraph) -> Result<Self::State, String>;
 
-    let mut path = vec![start];
-    let mut next_options: Vec<Vec<usize>> =
-        vec![graph.cells[start].links.iter().cloned().collect()];
+    /// The state after entering `cave`, or `None` if it may not be entered.
+    fn enter(&self, graph: &CaveGraph, state: &Self::State, cave: CaveId) -> Option<Self::State>;
+}
 
-    let can_visit = |path: &Vec<usize>, tgt: usize| -> bool {
-        let d = graph.get_cell_data(tgt).unwrap();
-        match d.cavetype {
-            CaveCellType::BigCave => true,
-            CaveCellType::SmallCave => !path.contains(&tgt),
-        }
-    };
+/// Small caves may be visited once, except that any `k` of them may be
+/// visited up to `m` times each. Big caves may be visited freely.
+struct SmallCaves {
+    k: usize,
+    m: u8,
+}
+
+impl SmallCaves {
+    /// Bits needed to count one revisited cave's visits beyond the second.
+    fn extra_bits(&self) -> u32 {
+        u8::BITS - self.m.saturating_sub(2).leading_zeros()
+    }
+}
+
+/// The small caves a path has visited, as bitmasks over their `small_index`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct SmallCaveVisits {
+    visited: u64,
+    /// Caves visited more than once. With `m == 2`, once all `k` revisits
+    /// are used no visited cave may be entered again, whichever was
+    /// revisited, so this saturates to every bit and such states share a
+    /// memo entry.
+    revisited: u64,
+    /// Visits beyond the second to each revisited cave, packed
+    /// `extra_bits` wide in bit order. Always zero when `m <= 2`.
+    extra: u64,
+}
 
-    loop {
-        if path.is_empty() {
-            break;
+impl VisitPolicy for SmallCaves {
+    type State = SmallCaveVisits;
+
+    fn initial(&self, graph: &CaveGraph) -> Result<Self::State, String> {
+        if graph.small_caves > 64 {
+            return Err("More than 64 small caves".to_string());
+        }
+        if self.k.min(graph.small_caves) * self.extra_bits() as usize > 64 {
+            return Err(format!(
+                "Too many revisits to count: k={} m={}",
+                self.k, self.m
+            ));
         }
+        Ok(SmallCaveVisits {
+            visited: 0,
+            revisited: 0,
+            extra: 0,
+        })
+    }
 
-        if let Some(next) = next_options.last_mut().unwrap().pop() {
-            if next == end {
-                unique_paths += 1;
-            } else if can_visit(&path, next) {
-                path.push(next);
-                next_options.push(graph.cells[next].links.iter().cloned().collect());
+    fn enter(&self, graph: &CaveGraph, state: &Self::State, cave: CaveId) -> Option<Self::State> {
+        let Some(index) = graph.cells[cave].small_index else {
+            return Some(*state);
+        };
+        let bit = 1 << index;
+        let width = self.extra_bits();
+        let shift = (state.revisited & (bit - 1)).count_ones() * width;
+        let mut state = *state;
+        if state.visited & bit == 0 {
+            state.visited |= bit;
+        } else if state.revisited & bit != 0 {
+            let extra = (state.extra >> shift) & ((1 << width) - 1);
+            if self.m <= 2 || extra + 2 >= self.m as u64 {
+                return None;
             }
+            state.extra += 1 << shift;
         } else {
-            next_options.pop();
-            path.pop();
+            if (state.revisited.count_ones() as usize) >= self.k || self.m < 2 {
+                return None;
+            }
+            state.revisited |= bit;
+            if self.m > 2 {
+                // Make room for this cave's count among the others
+                let above = (state.extra >> shift).checked_shl(shift + width);
+                state.extra = (state.extra & ((1 << shift) - 1)) | above.unwrap_or(0);
+            } else if state.revisited.count_ones() as usize == self.k {
+                state.revisited = u64::MAX;
+            }
         }
+        Some(state)
     }
+}
 
-    unique_paths
+/// Each named cave may be visited up to its own limit. Other small caves
+/// may be visited once and other big caves freely.
+struct CaveLimits {
+    limits: HashMap<String, usize>,
 }
 
-// 4186 correct
+impl VisitPolicy for CaveLimits {
+    /// Visits so far to each limited cave.
+    type State = Vec<usize>;
+
+    fn initial(&self, graph: &CaveGraph) -> Result<Self::State, String> {
+        Ok(vec![0; graph.cells.len()])
+    }
 
-fn part2(graph: &CaveGraph) -> usize {
-    let start = graph.get_cell("start").unwrap();
-    let end = graph.get_cell("end").unwrap();
+    fn enter(&self, graph: &CaveGraph, state: &Self::State, cave: CaveId) -> Option<Self::State> {
+        let cell = &graph.cells[cave];
+        let limit = match (self.limits.get(&cell.cavename), cell.cavetype) {
+            (Some(&limit), _) => limit,
+            (None, CaveCellType::SmallCave) => 1,
+            (None, CaveCellType::BigCave) => return Some(state.clone()),
+        };
+        (state[cave] < limit).then(|| {
+            let mut state = state.clone();
+            state[cave] += 1;
+            state
+        })
+    }
+}
 
-    let mut doublevisit = false;
+/// Another policy, with some caves never entered.
+struct Forbidden<P> {
+    policy: P,
+    caves: Vec<String>,
+}
 
-    let mut unique_paths = 0;
+impl<P: VisitPolicy> VisitPolicy for Forbidden<P> {
+    type State = P::State;
 
-    let mut path = vec![start];
-    let mut next_options: Vec<Vec<usize>> =
-        vec![graph.cells[start].links.iter().cloned().collect()];
+    fn initial(&self, graph: &CaveGraph) -> Result<Self::State, String> {
+        self.policy.initial(graph)
+    }
 
-    let can_visit = |path: &Vec<usize>, doublevisit: bool, tgt: usize| -> bool {
-        let d = graph.get_cell_data(tgt).unwrap();
-        match d.cavetype {
-            CaveCellType::BigCave => true,
-            CaveCellType::SmallCave => {
-                (doublevisit == false && tgt != start) || !path.contains(&tgt)
-            }
+    fn enter(&self, graph: &CaveGraph, state: &Self::State, cave: CaveId) -> Option<Self::State> {
+        if self.caves.contains(&graph.cells[cave].cavename) {
+            None
+        } else {
+            self.policy.enter(graph, state, cave)
         }
-    };
+    }
+}
 
-    loop {
-        if path.is_empty() {
-            break;
+/// Path counts memoised by (current cave, policy state), with `None` while
+/// a count is still in progress.
+type PathMemo<S> = HashMap<(CaveId, S), Option<usize>>;
+
+impl CaveGraph {
+    /// Counts the paths from `from` to `to` allowed by `policy`. A path never
+    /// returns to `from` and stops on reaching `to`. Takes time bounded by
+    /// the number of states rather than the number of paths, and fails if a
+    /// path could return to an earlier state, as when the policy lets two
+    /// linked big caves be bounced between forever.
+    fn count_paths<P: VisitPolicy>(
+        &self,
+        from: &str,
+        to: &str,
+        policy: &P,
+    ) -> Result<usize, String> {
+        let start = self.get_cell(from).ok_or(format!("No cave {}", from))?;
+        let end = self.get_cell(to).ok_or(format!("No cave {}", to))?;
+        let state = match policy.enter(self, &policy.initial(self)?, start) {
+            Some(state) => state,
+            None => return Ok(0),
+        };
+        self.count_from(start, state, (start, end), policy, &mut PathMemo::new())
+    }
+
+    fn count_from<P: VisitPolicy>(
+        &self,
+        cave: CaveId,
+        state: P::State,
+        (start, end): (CaveId, CaveId),
+        policy: &P,
+        memo: &mut PathMemo<P::State>,
+    ) -> Result<usize, String> {
+        if cave == end {
+            return Ok(1);
+        }
+        let key = (cave, state);
+        match memo.get(&key) {
+            Some(Some(count)) => return Ok(*count),
+            Some(None) => {
+                return Err(format!(
+                    "Paths can loop forever through {}",
+                    self.cells[cave].cavename
+                ))
+            }
+            None => {}
         }
+        memo.insert(key.clone(), None);
+        let mut count = 0;
+        for &next in &self.cells[cave].links {
+            if next == start {
+                continue;
+            }
+            if let Some(state) = policy.enter(self, &key.1, next) {
+                count += self.count_from(next, state, (start, end), policy, memo)?;
+            }
+        }
+        memo.insert(key, Some(count));
+        Ok(count)
+    }
+}
 
-        if let Some(next) = next_options.last_mut().unwrap().pop() {
-            if next == end {
-                unique_paths += 1;
-            } else if can_visit(&path, doublevisit, next) {
-                if graph.get_cell_data(next).unwrap().cavetype == CaveCellType::SmallCave
-                    && path.contains(&next)
-                {
-                    doublevisit = true;
-                }
-                path.push(next);
-                next_options.push(graph.cells[next].links.iter().cloned().collect());
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PathOrder {
+    DepthFirst,
+    /// Fewest caves first. Partial paths are kept breadth-first, so this
+    /// needs more memory.
+    ShortestFirst,
+}
+
+/// Lazily walks the paths allowed by a policy, yielding each as its cave
+/// names.
+struct Paths<'a, P: VisitPolicy> {
+    graph: &'a CaveGraph,
+    policy: &'a P,
+    order: PathOrder,
+    start: CaveId,
+    end: CaveId,
+    /// Partial paths still to extend, with the policy state after each.
+    frontier: VecDeque<(Vec<CaveId>, P::State)>,
+}
+
+impl<'a, P: VisitPolicy> Iterator for Paths<'a, P> {
+    type Item = Vec<&'a str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (path, state) = match self.order {
+                PathOrder::DepthFirst => self.frontier.pop_back()?,
+                PathOrder::ShortestFirst => self.frontier.pop_front()?,
+            };
+            let cave = *path.last().unwrap();
+            if cave == self.end {
+                let graph = self.graph;
+                return Some(
+                    path.iter()
+                        .map(|&id| graph.cells[id].cavename.as_str())
+                        .collect(),
+                );
             }
-        } else {
-            next_options.pop();
-            let id = path.pop().unwrap();
-            if graph.get_cell_data(id).unwrap().cavetype == CaveCellType::SmallCave
-                && path.contains(&id)
-            {
-                doublevisit = false;
+            let mut extended: Vec<_> = self.graph.cells[cave]
+                .links
+                .iter()
+                .filter(|&&next| next != self.start)
+                .filter_map(|&next| {
+                    let state = self.policy.enter(self.graph, &state, next)?;
+                    let mut path = path.clone();
+                    path.push(next);
+                    Some((path, state))
+                })
+                .collect();
+            // Depth-first pops from the back, so push so as to visit links in order
+            if self.order == PathOrder::DepthFirst {
+                extended.reverse();
             }
+            self.frontier.extend(extended);
         }
     }
+}
 
-    unique_paths
+impl CaveGraph {
+    /// Every path `count_paths` would count, as an iterator. Where the policy
+    /// allows endless bouncing between caves there are infinitely many, and
+    /// only shortest-first order is sure to keep yielding them.
+    fn paths<'a, P: VisitPolicy>(
+        &'a self,
+        from: &str,
+        to: &str,
+        policy: &'a P,
+        order: PathOrder,
+    ) -> Result<Paths<'a, P>, String> {
+        let start = self.get_cell(from).ok_or(format!("No cave {}", from))?;
+        let end = self.get_cell(to).ok_or(format!("No cave {}", to))?;
+        let frontier = policy
+            .enter(self, &policy.initial(self)?, start)
+            .map(|state| (vec![start], state))
+            .into_iter()
+            .collect();
+        Ok(Paths {
+            graph: self,
+            policy,
+            order,
+            start,
+            end,
+            frontier,
+        })
+    }
+}
+
+/// Prints the count and the first `show` paths for some settings.
+fn report<P: VisitPolicy>(
+    graph: &CaveGraph,
+    (from, to): (&str, &str),
+    policy: &P,
+    show: usize,
+    order: PathOrder,
+) -> Result<(), String> {
+    println!(
+        "Paths from {} to {}: {}",
+        from,
+        to,
+        graph.count_paths(from, to, policy)?
+    );
+    for path in graph.paths(from, to, policy, order)?.take(show) {
+        println!("{}", path.join(","));
+    }
+    Ok(())
+}
+
+fn part1(graph: &CaveGraph) -> Result<usize, String> {
+    graph.count_paths("start", "end", &SmallCaves { k: 0, m: 1 })
+}
+
+// 4186 correct
+
+fn part2(graph: &CaveGraph) -> Result<usize, String> {
+    graph.count_paths("start", "end", &SmallCaves { k: 1, m: 2 })
 }
 
 // 118890 too high because I double-visited start
@@ -192,6 +456,187 @@ fn main() {
         .parse()
         .unwrap();
 
-    println!("Part 1: {}", part1(&input));
-    println!("Part 2: {}", part2(&input));
+    println!("Part 1: {}", part1(&input).unwrap());
+    println!("Part 2: {}", part2(&input).unwrap());
+
+    // Optional traversal settings: "from=cave", "to=cave", "k=N" and "m=N"
+    // small caves revisited, "limit=cave:N" and "forbid=cave". "show=N" lists
+    // N paths, in "order=depth" or "order=shortest", and "dot=file" writes
+    // the graph for Graphviz.
+    let (mut from, mut to) = ("start", "end");
+    let mut small = SmallCaves { k: 0, m: 1 };
+    let mut limits = HashMap::new();
+    let mut forbidden = Vec::new();
+    let (mut show, mut order) = (0, PathOrder::DepthFirst);
+    for arg in &args[2..] {
+        match arg.split_once('=') {
+            Some(("from", cave)) => from = cave,
+            Some(("to", cave)) => to = cave,
+            Some(("k", n)) => small.k = n.parse().unwrap(),
+            Some(("m", n)) => small.m = n.parse().unwrap(),
+            Some(("limit", limit)) => {
+                let (cave, n) = limit.split_once(':').expect("Limits look like cave:N");
+                limits.insert(cave.to_string(), n.parse().unwrap());
+            }
+            Some(("forbid", cave)) => forbidden.push(cave.to_string()),
+            Some(("show", n)) => show = n.parse().unwrap(),
+            Some(("order", "depth")) => order = PathOrder::DepthFirst,
+            Some(("order", "shortest")) => order = PathOrder::ShortestFirst,
+            Some(("dot", file)) => {
+                std::fs::write(file, input.to_dot()).expect("Failed to write DOT file")
+            }
+            _ => panic!("Unknown setting {}", arg),
+        }
+    }
+    if args.len() > 2 {
+        let result = if limits.is_empty() {
+            let policy = Forbidden {
+                policy: small,
+                caves: forbidden,
+            };
+            report(&input, (from, to), &policy, show, order)
+        } else {
+            let policy = Forbidden {
+                policy: CaveLimits { limits },
+                caves: forbidden,
+            };
+            report(&input, (from, to), &policy, show, order)
+        };
+        if let Err(e) = result {
+            println!("{}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_example() {
+        let graph: CaveGraph = include_str!("../example1.txt").parse().unwrap();
+        assert_eq!(part1(&graph), Ok(10));
+        assert_eq!(part2(&graph), Ok(36));
+    }
+
+    #[test]
+    fn test_policies() {
+        let graph: CaveGraph = include_str!("../example1.txt").parse().unwrap();
+        let forbid_a = Forbidden {
+            policy: SmallCaves { k: 0, m: 1 },
+            caves: vec!["A".to_string()],
+        };
+        assert_eq!(graph.count_paths("start", "end", &forbid_a), Ok(1));
+
+        // Each path of part 2 doubles back through at most one of b, c and d
+        let doubled: usize = ["b", "c", "d"]
+            .iter()
+            .map(|cave| {
+                let limits = HashMap::from([(cave.to_string(), 2)]);
+                graph
+                    .count_paths("start", "end", &CaveLimits { limits })
+                    .unwrap()
+            })
+            .sum();
+        assert_eq!(doubled - 2 * part1(&graph).unwrap(), part2(&graph).unwrap());
+
+        let twice = SmallCaves { k: 3, m: 2 };
+        let once = SmallCaves { k: 0, m: 1 };
+        assert!(graph.count_paths("start", "end", &twice).unwrap() > 36);
+        // A-b-d, A-start-b-d and A-end-b-d, as end is not an endpoint here
+        assert_eq!(graph.count_paths("A", "d", &once), Ok(3));
+        assert!(graph.count_paths("A", "nowhere", &once).is_err());
+    }
+
+    #[test]
+    fn test_revisits() {
+        let graph: CaveGraph = include_str!("../example1.txt").parse().unwrap();
+        let limited = |caves: &[&str], m: usize| {
+            let limits = caves.iter().map(|cave| (cave.to_string(), m)).collect();
+            graph
+                .count_paths("start", "end", &CaveLimits { limits })
+                .unwrap()
+        };
+        let singles = |m| ["b", "c", "d"].map(|cave| limited(&[cave], m));
+        let pairs = |m| [["b", "c"], ["b", "d"], ["c", "d"]].map(|caves| limited(&caves, m));
+        let once = part1(&graph).unwrap();
+        for m in 2..=4 {
+            let small = |k| graph.count_paths("start", "end", &SmallCaves { k, m });
+            let singles: usize = singles(m as usize).iter().sum();
+            let pairs: usize = pairs(m as usize).iter().sum();
+            assert_eq!(small(1), Ok(singles - 2 * once));
+            assert_eq!(small(2), Ok(pairs - singles + once));
+            assert_eq!(small(3), Ok(limited(&["b", "c", "d"], m as usize)));
+        }
+    }
+
+    #[test]
+    fn test_path_iterator() {
+        let graph: CaveGraph = include_str!("../example1.txt").parse().unwrap();
+        let once = SmallCaves { k: 0, m: 1 };
+        let paths: Vec<Vec<&str>> = graph
+            .paths("start", "end", &once, PathOrder::DepthFirst)
+            .unwrap()
+            .collect();
+        assert_eq!(paths.len(), 10);
+        assert_eq!(paths[0], vec!["start", "A", "b", "A", "c", "A", "end"]);
+        assert!(paths.contains(&vec!["start", "b", "end"]));
+
+        let twice = SmallCaves { k: 1, m: 2 };
+        let shortest: Vec<Vec<&str>> = graph
+            .paths("start", "end", &twice, PathOrder::ShortestFirst)
+            .unwrap()
+            .collect();
+        assert_eq!(shortest.len(), 36);
+        assert_eq!(
+            shortest[..2],
+            [vec!["start", "A", "end"], vec!["start", "b", "end"]]
+        );
+        assert!(shortest.windows(2).all(|w| w[0].len() <= w[1].len()));
+
+        // Endless bouncing still yields paths lazily when shortest first
+        let bouncing: CaveGraph = "start-A\nA-B\nB-end".parse().unwrap();
+        let paths = bouncing
+            .paths("start", "end", &once, PathOrder::ShortestFirst)
+            .unwrap();
+        let lengths: Vec<usize> = paths.take(3).map(|p| p.len()).collect();
+        assert_eq!(lengths, vec![4, 6, 8]);
+    }
+
+    #[test]
+    fn test_dot() {
+        let graph: CaveGraph = "start-A\nA-b\nb-end".parse().unwrap();
+        assert_eq!(graph.get_cell("b"), Some(2));
+        assert_eq!(
+            graph.to_dot(),
+            "graph caves {\n    \"start\" [shape=ellipse];\n    \"A\" [shape=box, style=filled];\n    \"b\" [shape=ellipse];\n    \"end\" [shape=ellipse];\n    \"start\" -- \"A\";\n    \"A\" -- \"b\";\n    \"b\" -- \"end\";\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_many_paths() {
+        // Any ordering of any subset of the small caves, each reached via A
+        let mut input = "start-A\nA-end".to_string();
+        for i in 0..15 {
+            input += &format!("\nA-c{}", i);
+        }
+        let graph: CaveGraph = input.parse().unwrap();
+        assert_eq!(part1(&graph), Ok(3554627472076));
+    }
+
+    #[test]
+    fn test_bad_graphs() {
+        let graph: CaveGraph = "start-A\nA-B\nB-end".parse().unwrap();
+        assert!(part1(&graph).is_err());
+        let graph: CaveGraph = "start-a\na-b".parse().unwrap();
+        assert!(part1(&graph).is_err());
+
+        // Bouncing is fine while the policy limits it
+        let graph: CaveGraph = "start-A\nA-B\nB-end".parse().unwrap();
+        let limits = HashMap::from([("A".to_string(), 2), ("B".to_string(), 2)]);
+        assert_eq!(
+            graph.count_paths("start", "end", &CaveLimits { limits }),
+            Ok(2)
+        );
+    }
 }
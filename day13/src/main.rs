@@ -1,4 +1,4 @@
-use std::str::FromStr;
+use std::{collections::HashSet, str::FromStr};
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 enum FoldInstruction {
@@ -28,70 +28,289 @@ impl FromStr for FoldInstruction {
 type Dot = (i32, i32);
 type Dots = Vec<Dot>;
 
-fn do_fold(mut dots: Dots, instr: FoldInstruction) -> Dots {
-    match instr {
-        FoldInstruction::AlongY(py) => {
-            dots.sort_unstable_by_key(|&dot| dot.1);
-            let partition = dots
-                .binary_search_by_key(&py, |&dot| dot.1)
-                .unwrap_or_else(|err| err);
-            let (above_fold, below_fold) = dots.split_at_mut(partition);
-            let mut above_fold = above_fold.to_vec();
-            below_fold
-                .into_iter()
-                .map(|(x, y)| (*x, 2 * py - *y))
-                .for_each(|dot| {
-                    if !above_fold.contains(&dot) {
-                        above_fold.push(dot);
-                    }
-                });
-            above_fold
+/// A fold, with what is needed to undo it.
+struct FoldRecord {
+    instr: FoldInstruction,
+    /// Size of the paper along the folded axis before the fold.
+    extent: i32,
+    /// How far the folded coordinates were moved to keep them non-negative.
+    shift: i32,
+}
+
+/// Where a coordinate along the folded axis lands, or `None` on the line.
+fn fold_coord(c: i32, line: i32, shift: i32) -> Option<i32> {
+    match c.cmp(&line) {
+        std::cmp::Ordering::Less => Some(c + shift),
+        std::cmp::Ordering::Equal => None,
+        std::cmp::Ordering::Greater => Some(2 * line - c + shift),
+    }
+}
+
+/// A transparent sheet of dots, folded the far side over onto the near side
+/// of each line.
+struct Paper {
+    dots: HashSet<Dot>,
+    width: i32,
+    height: i32,
+    original: HashSet<Dot>,
+    history: Vec<FoldRecord>,
+}
+
+impl Paper {
+    /// A sheet just large enough for the dots, which must not be negative.
+    fn new(dots: &[Dot]) -> Result<Self, String> {
+        if let Some(dot) = dots.iter().find(|dot| dot.0 < 0 || dot.1 < 0) {
+            return Err(format!("Dot {:?} is off the paper", dot));
+        }
+        let dots: HashSet<Dot> = dots.iter().copied().collect();
+        Ok(Paper {
+            width: dots.iter().map(|dot| dot.0 + 1).max().unwrap_or(0),
+            height: dots.iter().map(|dot| dot.1 + 1).max().unwrap_or(0),
+            original: dots.clone(),
+            dots,
+            history: Vec::new(),
+        })
+    }
+
+    /// Folds along a line anywhere inside the paper. If the far side is the
+    /// longer it overhangs the near edge, and coordinates are shifted so the
+    /// folded paper still starts at zero.
+    fn fold(&mut self, instr: FoldInstruction) -> Result<(), String> {
+        let (line, extent) = match instr {
+            FoldInstruction::AlongX(x) => (x, self.width),
+            FoldInstruction::AlongY(y) => (y, self.height),
+        };
+        if !(0..extent).contains(&line) {
+            return Err(format!("{:?} is off the paper", instr));
         }
+        let folded_extent = line.max(extent - 1 - line);
+        let shift = folded_extent - line;
 
-        FoldInstruction::AlongX(px) => {
-            dots.sort_unstable_by_key(|&dot| dot.0);
-            let partition = dots
-                .binary_search_by_key(&px, |&dot| dot.0)
-                .unwrap_or_else(|err| err);
-            let (left_fold, right_fold) = dots.split_at_mut(partition);
-            let mut left_fold = left_fold.to_vec();
-            right_fold
+        let mut dots = HashSet::with_capacity(self.dots.len());
+        for &(x, y) in &self.dots {
+            let dot = match instr {
+                FoldInstruction::AlongX(_) => fold_coord(x, line, shift).map(|x| (x, y)),
+                FoldInstruction::AlongY(_) => fold_coord(y, line, shift).map(|y| (x, y)),
+            };
+            dots.insert(dot.ok_or(format!("Dot ({}, {}) lies on {:?}", x, y, instr))?);
+        }
+        self.dots = dots;
+        match instr {
+            FoldInstruction::AlongX(_) => self.width = folded_extent,
+            FoldInstruction::AlongY(_) => self.height = folded_extent,
+        }
+        self.history.push(FoldRecord {
+            instr,
+            extent,
+            shift,
+        });
+        Ok(())
+    }
+
+    /// The visible dots, sorted by row then column.
+    fn dots(&self) -> Dots {
+        let mut dots: Dots = self.dots.iter().copied().collect();
+        dots.sort_unstable_by_key(|&(x, y)| (y, x));
+        dots
+    }
+
+    /// Every original dot which was folded onto `dot`, sorted by row then
+    /// column.
+    fn origins(&self, dot: Dot) -> Dots {
+        let mut positions = vec![dot];
+        for record in self.history.iter().rev() {
+            // Each position could have come from either side of the line
+            let unfold = |c: i32, line: i32| {
+                let near = c - record.shift;
+                [near, 2 * line - near]
+                    .into_iter()
+                    .filter(move |&c| (0..record.extent).contains(&c) && c != line)
+            };
+            positions = positions
                 .into_iter()
-                .map(|(x, y)| (2 * px - *x, *y))
-                .for_each(|dot| {
-                    if !left_fold.contains(&dot) {
-                        left_fold.push(dot);
+                .flat_map(|(x, y)| -> Vec<Dot> {
+                    match record.instr {
+                        FoldInstruction::AlongX(line) => unfold(x, line).map(|x| (x, y)).collect(),
+                        FoldInstruction::AlongY(line) => unfold(y, line).map(|y| (x, y)).collect(),
                     }
-                });
-            left_fold
+                })
+                .collect();
         }
+        positions.retain(|dot| self.original.contains(dot));
+        positions.sort_unstable_by_key(|&(x, y)| (y, x));
+        positions
     }
 }
 
-fn part1(dots: Dots, instr: FoldInstruction) -> usize {
-    do_fold(dots, instr).len()
+fn part1(dots: &[Dot], instr: FoldInstruction) -> Result<usize, String> {
+    let mut paper = Paper::new(dots)?;
+    paper.fold(instr)?;
+    Ok(paper.dots.len())
 }
 
 // 839 too high because I wasn't folding correctly
 // 704 correct
 
-fn part2(dots: Dots, instrs: Vec<FoldInstruction>) -> String {
-    let dots: Dots = instrs.into_iter().fold(dots, do_fold);
-    assert!(dots.iter().all(|dot| dot.0 >= 0 && dot.1 >= 0));
-    let max_x = dots.iter().map(|dot| dot.0).max().unwrap();
-    let max_y = dots.iter().map(|dot| dot.1).max().unwrap();
+/// A fixed-pitch bitmap font, with glyphs drawn in `#` and `.` rows. Most
+/// glyphs leave a blank column before the next letter, but a wide glyph may
+/// fill its whole cell.
+struct Font {
+    height: usize,
+    /// Columns from the start of one letter to the start of the next.
+    stride: usize,
+    glyphs: &'static [(char, &'static str)],
+}
+
+/// The usual 4x6 letters, and a 5x6 Y.
+const SMALL_FONT: Font = Font {
+    height: 6,
+    stride: 5,
+    glyphs: &[
+        ('A', ".##.\n#..#\n#..#\n####\n#..#\n#..#"),
+        ('B', "###.\n#..#\n###.\n#..#\n#..#\n###."),
+        ('C', ".##.\n#..#\n#...\n#...\n#..#\n.##."),
+        ('E', "####\n#...\n###.\n#...\n#...\n####"),
+        ('F', "####\n#...\n###.\n#...\n#...\n#..."),
+        ('G', ".##.\n#..#\n#...\n#.##\n#..#\n.###"),
+        ('H', "#..#\n#..#\n####\n#..#\n#..#\n#..#"),
+        ('I', ".###\n..#.\n..#.\n..#.\n..#.\n.###"),
+        ('J', "..##\n...#\n...#\n...#\n#..#\n.##."),
+        ('K', "#..#\n#.#.\n##..\n#.#.\n#.#.\n#..#"),
+        ('L', "#...\n#...\n#...\n#...\n#...\n####"),
+        ('O', ".##.\n#..#\n#..#\n#..#\n#..#\n.##."),
+        ('P', "###.\n#..#\n#..#\n###.\n#...\n#..."),
+        ('R', "###.\n#..#\n#..#\n###.\n#.#.\n#..#"),
+        ('S', ".###\n#...\n#...\n.##.\n...#\n###."),
+        ('U', "#..#\n#..#\n#..#\n#..#\n#..#\n.##."),
+        ('Y', "#...#\n#...#\n.#.#.\n..#..\n..#..\n..#.."),
+        ('Z', "####\n...#\n..#.\n.#..\n#...\n####"),
+    ],
+};
+
+/// The taller 6x10 letters.
+const LARGE_FONT: Font = Font {
+    height: 10,
+    stride: 8,
+    glyphs: &[
+        (
+            'A',
+            "..##..\n.#..#.\n#....#\n#....#\n#....#\n######\n#....#\n#....#\n#....#\n#....#",
+        ),
+        (
+            'B',
+            "#####.\n#....#\n#....#\n#....#\n#####.\n#....#\n#....#\n#....#\n#....#\n#####.",
+        ),
+        (
+            'C',
+            ".####.\n#....#\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#....#\n.####.",
+        ),
+        (
+            'E',
+            "######\n#.....\n#.....\n#.....\n#####.\n#.....\n#.....\n#.....\n#.....\n######",
+        ),
+        (
+            'F',
+            "######\n#.....\n#.....\n#.....\n#####.\n#.....\n#.....\n#.....\n#.....\n#.....",
+        ),
+        (
+            'G',
+            ".####.\n#....#\n#.....\n#.....\n#.....\n#..###\n#....#\n#....#\n#...##\n.###.#",
+        ),
+        (
+            'H',
+            "#....#\n#....#\n#....#\n#....#\n######\n#....#\n#....#\n#....#\n#....#\n#....#",
+        ),
+        (
+            'J',
+            "...###\n....#.\n....#.\n....#.\n....#.\n....#.\n....#.\n#...#.\n#...#.\n.###..",
+        ),
+        (
+            'K',
+            "#....#\n#...#.\n#..#..\n#.#...\n##....\n##....\n#.#...\n#..#..\n#...#.\n#....#",
+        ),
+        (
+            'L',
+            "#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n######",
+        ),
+        (
+            'N',
+            "#....#\n##...#\n##...#\n#.#..#\n#.#..#\n#..#.#\n#..#.#\n#...##\n#...##\n#....#",
+        ),
+        (
+            'P',
+            "#####.\n#....#\n#....#\n#....#\n#####.\n#.....\n#.....\n#.....\n#.....\n#.....",
+        ),
+        (
+            'R',
+            "#####.\n#....#\n#....#\n#....#\n#####.\n#..#..\n#...#.\n#...#.\n#....#\n#....#",
+        ),
+        (
+            'X',
+            "#....#\n#....#\n.#..#.\n.#..#.\n..##..\n..##..\n.#..#.\n.#..#.\n#....#\n#....#",
+        ),
+        (
+            'Z',
+            "######\n.....#\n.....#\n....#.\n...#..\n..#...\n.#....\n#.....\n#.....\n######",
+        ),
+    ],
+};
+
+/// Draws the dots as rows of `#` and `.`, from (0, 0) so that letters stay
+/// aligned to their cells. The dots must not be negative.
+fn picture(dots: &[Dot]) -> Vec<String> {
+    let (Some(max_x), Some(max_y)) = (
+        dots.iter().map(|dot| dot.0).max(),
+        dots.iter().map(|dot| dot.1).max(),
+    ) else {
+        return vec![];
+    };
     let mut grid = vec![vec!['.'; max_x as usize + 1]; max_y as usize + 1];
     for dot in dots {
         grid[dot.1 as usize][dot.0 as usize] = '#';
     }
-    [String::default()]
+    grid.into_iter()
+        .map(|row| row.into_iter().collect())
+        .collect()
+}
+
+/// Reads the letters the dots spell, in whichever font matches their height.
+fn ocr(dots: &[Dot]) -> Result<String, String> {
+    if let Some(dot) = dots.iter().find(|dot| dot.0 < 0 || dot.1 < 0) {
+        return Err(format!("Dot {:?} is off the paper", dot));
+    }
+    let rows = picture(dots);
+    let font = [SMALL_FONT, LARGE_FONT]
         .into_iter()
-        .chain(
-            grid.into_iter()
-                .map(|row| row.into_iter().collect::<String>()),
-        )
-        .collect::<Vec<_>>()
-        .join("\n")
+        .find(|font| font.height == rows.len())
+        .ok_or(format!("No font is {} dots tall", rows.len()))?;
+    let width = rows[0].len();
+    (0..width.div_ceil(font.stride))
+        .map(|i| {
+            // The last letter is cut short if its right columns are empty, so
+            // cells and glyphs are both padded out to the full pitch
+            let pad = |row: &str| format!("{:.<w$}", row, w = font.stride);
+            let start = i * font.stride;
+            let end = (start + font.stride).min(width);
+            let cell = rows
+                .iter()
+                .map(|row| pad(&row[start..end]))
+                .collect::<Vec<_>>()
+                .join("\n");
+            font.glyphs
+                .iter()
+                .find(|(_, glyph)| glyph.lines().map(pad).collect::<Vec<_>>().join("\n") == cell)
+                .map(|&(c, _)| c)
+                .ok_or(format!("Unrecognised letter {}:\n{}", i + 1, cell))
+        })
+        .collect()
+}
+
+fn part2(dots: &[Dot], instrs: &[FoldInstruction]) -> Result<String, String> {
+    let mut paper = Paper::new(dots)?;
+    for &instr in instrs {
+        paper.fold(instr)?;
+    }
+    ocr(&paper.dots())
 }
 
 // #..#..##...##....##.###..####.#..#..##.
@@ -127,6 +346,173 @@ fn main() {
         .collect::<Result<_, _>>()
         .unwrap();
 
-    println!("Part 1: {}", part1(dots.clone(), folds[0]));
-    println!("Part 2: {}", part2(dots, folds));
+    println!("Part 1: {}", part1(&dots, folds[0]).unwrap());
+    match part2(&dots, &folds) {
+        Ok(letters) => println!("Part 2: {}", letters),
+        Err(e) => println!("Part 2: {}", e),
+    }
+
+    // "origins x,y" lists the original dots folded onto a final dot
+    if args.get(2).map(String::as_str) == Some("origins") {
+        let (x, y) = args[3].split_once(',').expect("Give the dot as x,y");
+        let mut paper = Paper::new(&dots).unwrap();
+        for &instr in &folds {
+            paper.fold(instr).unwrap();
+        }
+        println!(
+            "{:?}",
+            paper.origins((x.parse().unwrap(), y.parse().unwrap()))
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dots_of(rows: &[&str]) -> Dots {
+        let mut dots = Dots::new();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                if c == '#' {
+                    dots.push((x as i32, y as i32));
+                }
+            }
+        }
+        dots
+    }
+
+    fn parse(input: &str) -> (Dots, Vec<FoldInstruction>) {
+        let (dots, folds) = input.split_once("\n\n").unwrap();
+        let dots = dots
+            .lines()
+            .map(|l| {
+                let (x, y) = l.split_once(',').unwrap();
+                (x.parse().unwrap(), y.parse().unwrap())
+            })
+            .collect();
+        (dots, folds.lines().map(|l| l.parse().unwrap()).collect())
+    }
+
+    #[test]
+    fn test_example() {
+        let (dots, folds) = parse(include_str!("../example1.txt"));
+        assert_eq!(part1(&dots, folds[0]), Ok(17));
+        let mut paper = Paper::new(&dots).unwrap();
+        for &instr in &folds {
+            paper.fold(instr).unwrap();
+        }
+        assert_eq!(paper.dots().len(), 16);
+        assert_eq!((paper.width, paper.height), (5, 7));
+        assert_eq!(paper.origins((0, 0)), vec![(0, 14)]);
+        assert_eq!(paper.origins((2, 4)), vec![(8, 4), (8, 10)]);
+        assert_eq!(paper.origins((2, 2)), vec![]);
+    }
+
+    #[test]
+    fn test_fold_near_edge() {
+        let mut paper = Paper::new(&[(0, 0), (0, 2), (2, 5)]).unwrap();
+        paper.fold(FoldInstruction::AlongY(1)).unwrap();
+        // The far side overhangs by three rows, so everything moves down three
+        assert_eq!(paper.dots(), vec![(2, 0), (0, 3)]);
+        assert_eq!(paper.height, 4);
+        assert_eq!(paper.origins((0, 3)), vec![(0, 0), (0, 2)]);
+        assert_eq!(paper.origins((2, 0)), vec![(2, 5)]);
+
+        paper.fold(FoldInstruction::AlongX(1)).unwrap();
+        assert_eq!(paper.dots(), vec![(0, 0), (0, 3)]);
+        assert_eq!(paper.origins((0, 0)), vec![(2, 5)]);
+
+        assert!(paper.fold(FoldInstruction::AlongY(3)).is_err());
+        assert!(paper.fold(FoldInstruction::AlongX(5)).is_err());
+    }
+
+    #[test]
+    fn test_small_font() {
+        let dots = dots_of(&[
+            "#..#..##...##....##.###..####.#..#..##.",
+            "#..#.#..#.#..#....#.#..#.#....#..#.#..#",
+            "####.#....#..#....#.###..###..####.#...",
+            "#..#.#.##.####....#.#..#.#....#..#.#...",
+            "#..#.#..#.#..#.#..#.#..#.#....#..#.#..#",
+            "#..#..###.#..#..##..###..####.#..#..##.",
+        ]);
+        assert_eq!(ocr(&dots), Ok("HGAJBEHC".to_string()));
+    }
+
+    #[test]
+    fn test_leading_blank_column() {
+        // I's first column is empty, so cropping to the dots would misalign it
+        let rows = [
+            ".###.#..#",
+            "..#..#..#",
+            "..#..####",
+            "..#..#..#",
+            "..#..#..#",
+            ".###.#..#",
+        ];
+        assert_eq!(ocr(&dots_of(&rows)), Ok("IH".to_string()));
+        let paper = Paper::new(&dots_of(&rows)).unwrap();
+        assert_eq!(ocr(&paper.dots()), Ok("IH".to_string()));
+    }
+
+    /// The rows of a word set in a font, each letter in its own cell.
+    fn typeset(font: &Font, word: &str) -> Vec<String> {
+        let glyphs: Vec<&str> = word
+            .chars()
+            .map(|c| font.glyphs.iter().find(|g| g.0 == c).unwrap().1)
+            .collect();
+        (0..font.height)
+            .map(|y| {
+                glyphs
+                    .iter()
+                    .map(|glyph| format!("{:.<w$}", glyph.lines().nth(y).unwrap(), w = font.stride))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_every_glyph() {
+        for font in [SMALL_FONT, LARGE_FONT] {
+            for &(c, glyph) in font.glyphs {
+                let rows: Vec<&str> = glyph.lines().collect();
+                assert_eq!(ocr(&dots_of(&rows)), Ok(c.to_string()));
+            }
+            let alphabet: String = font.glyphs.iter().map(|g| g.0).collect();
+            let rows = typeset(&font, &alphabet);
+            let rows: Vec<&str> = rows.iter().map(String::as_str).collect();
+            assert_eq!(ocr(&dots_of(&rows)), Ok(alphabet));
+        }
+    }
+
+    #[test]
+    fn test_large_font() {
+        let glyph = |c: char| LARGE_FONT.glyphs.iter().find(|g| g.0 == c).unwrap().1;
+        let rows: Vec<String> = glyph('X')
+            .lines()
+            .zip(glyph('Z').lines())
+            .map(|(x, z)| format!("{}..{}", x, z))
+            .collect();
+        let rows: Vec<&str> = rows.iter().map(|row| row.as_str()).collect();
+        assert_eq!(ocr(&dots_of(&rows)), Ok("XZ".to_string()));
+    }
+
+    #[test]
+    fn test_unknown_glyph() {
+        let dots = dots_of(&[
+            ".##..#..#",
+            "#..#.#..#",
+            "#..#.####",
+            "####.#..#",
+            "#..#.#..#",
+            "#..#.##.#",
+        ]);
+        assert_eq!(
+            ocr(&dots),
+            Err("Unrecognised letter 2:\n#..#.\n#..#.\n####.\n#..#.\n#..#.\n##.#.".to_string())
+        );
+        // The example folds into a square, which is no letter of either font
+        assert!(ocr(&dots_of(&["#####", "#...#", "#...#", "#...#", "#####"])).is_err());
+    }
 }
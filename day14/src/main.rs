@@ -1,38 +1,78 @@
+use matpow::{BigUint, Count, Matrix, Mod};
 use minimax::minimax;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Display, Formatter};
 use std::str::FromStr;
 
-type Rule = ((char, char), char);
+/// A pair and the characters inserted between its two elements.
+type Rule = ((char, char), String);
 
+/// Parses "AB -> XYZ". The insertion may be any length, even empty.
 fn to_rule(s: &str) -> Result<Rule, String> {
-    if !s.is_ascii() {
+    let (pair, insertion) = s.split_once(" -> ").ok_or(format!(
+        "Rules must look like \"AB -> C\". Got string: \"{}\"",
+        s
+    ))?;
+    let pair: Vec<char> = pair.chars().collect();
+    if pair.len() != 2 {
         Err(format!(
-            "Non-ASCII characters are not allowed. Got string: \"{}\"",
+            "Rules must match a pair of characters. Got string: \"{}\"",
             s
         ))
-    } else if s.len() != 7 {
+    } else if insertion.chars().any(char::is_whitespace) {
         Err(format!(
-            "All rules must be exactly 7 characters long. Got string: \"{}\"",
-            s
-        ))
-    } else if s.chars().any(|c| c.is_ascii_lowercase()) {
-        Err(format!(
-            "All rules must be uppercase. Got string: \"{}\"",
+            "Insertions must not contain whitespace. Got string: \"{}\"",
             s
         ))
     } else {
-        let mut chars = s.chars();
-        let left = chars.next().unwrap();
-        let right = chars.next().unwrap();
-        let result = chars.nth(4).unwrap();
-        Ok(((left, right), result))
+        Ok(((pair[0], pair[1]), insertion.to_string()))
+    }
+}
+
+type Ruleset = HashMap<(char, char), String>;
+
+/// Parses one rule per line, reporting any pair given two different
+/// insertions. Repeating a rule exactly is harmless.
+fn parse_rules<'a>(lines: impl Iterator<Item = &'a str>) -> Result<Ruleset, String> {
+    let mut rules = Ruleset::new();
+    let mut lines_of: HashMap<(char, char), usize> = HashMap::new();
+    for (n, line) in lines.enumerate() {
+        let (pair, insertion) = to_rule(line).map_err(|e| format!("Rule {}: {}", n + 1, e))?;
+        match rules.get(&pair) {
+            Some(existing) if *existing != insertion => {
+                return Err(format!(
+                    "Rules {} and {} conflict: {}{} -> {} and {}{} -> {}",
+                    lines_of[&pair],
+                    n + 1,
+                    pair.0,
+                    pair.1,
+                    existing,
+                    pair.0,
+                    pair.1,
+                    insertion
+                ))
+            }
+            Some(_) => {}
+            None => {
+                lines_of.insert(pair, n + 1);
+                rules.insert(pair, insertion);
+            }
+        }
     }
+    Ok(rules)
 }
 
-type Ruleset = HashMap<(char, char), char>;
+/// What happens to a pair with no rule.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MissingRule {
+    Error,
+    /// The pair is left as it is.
+    PassThrough,
+}
 
 #[derive(Debug, Clone)]
 struct Polymer {
+    template: String,
     pairs: HashMap<(char, char), usize>,
     first: char,
 }
@@ -48,66 +88,252 @@ impl FromStr for Polymer {
                 pairs.entry(c).and_modify(|e| *e += 1).or_insert(1);
             });
 
-            Ok(Polymer { pairs, first })
+            Ok(Polymer {
+                template: s.to_string(),
+                pairs,
+                first,
+            })
         } else {
             Err("No characters in polymer string.".to_string())
         }
     }
 }
 
-fn step_polymer(polymer: &Polymer, rules: &Ruleset) -> Result<Polymer, String> {
-    let mut pairs = HashMap::new();
+impl Polymer {
+    /// The polymer itself after `steps` steps, for checking the counts on
+    /// short runs. Fails once it would be longer than `limit`.
+    fn materialise(
+        &self,
+        rules: &Ruleset,
+        missing: MissingRule,
+        steps: u64,
+        limit: usize,
+    ) -> Result<String, String> {
+        let mut polymer = self.template.clone();
+        for step in 1..=steps {
+            let chars: Vec<char> = polymer.chars().collect();
+            let mut next = String::from(chars[0]);
+            for w in chars.windows(2) {
+                match (rules.get(&(w[0], w[1])), missing) {
+                    (Some(insertion), _) => next.push_str(insertion),
+                    (None, MissingRule::PassThrough) => {}
+                    (None, MissingRule::Error) => {
+                        return Err(format!("Ruleset does not contain key: {:?}", (w[0], w[1])))
+                    }
+                }
+                next.push(w[1]);
+                if next.len() > limit {
+                    return Err(format!("Polymer is over {} long at step {}", limit, step));
+                }
+            }
+            polymer = next;
+        }
+        Ok(polymer)
+    }
+}
+
+/// Counts describing a polymer.
+struct PolymerStats<C> {
+    length: C,
+    /// Each element which appears and its count, sorted by element.
+    elements: Vec<(char, C)>,
+    /// Each pair which appears and its count, sorted by pair.
+    pairs: Vec<((char, char), C)>,
+}
 
-    for (&(left, right), &count) in &polymer.pairs {
-        if let Some(&result) = rules.get(&(left, right)) {
-            pairs
-                .entry((left, result))
-                .and_modify(|e| *e += count)
-                .or_insert(count);
-            pairs
-                .entry((result, right))
-                .and_modify(|e| *e += count)
-                .or_insert(count);
-        } else {
-            return Err(format!("Ruleset does not contain key: {:?}", (left, right)));
+impl<C: Count + Ord> PolymerStats<C> {
+    /// The elements with a given count, with that count.
+    fn tied(&self, count: &C) -> (Vec<char>, C) {
+        let elements = self
+            .elements
+            .iter()
+            .filter(|(_, c)| c == count)
+            .map(|&(element, _)| element)
+            .collect();
+        (elements, count.clone())
+    }
+
+    /// The most common elements, with their count.
+    fn most_common(&self) -> Option<(Vec<char>, C)> {
+        let (_, max) = minimax(self.elements.iter().map(|(_, count)| count))?;
+        Some(self.tied(max))
+    }
+
+    /// The least common elements, with their count.
+    fn least_common(&self) -> Option<(Vec<char>, C)> {
+        let (min, _) = minimax(self.elements.iter().map(|(_, count)| count))?;
+        Some(self.tied(min))
+    }
+}
+
+/// The rules compiled into a linear map on pair counts: each step, every
+/// pair's count flows to the pairs its insertion makes.
+struct PolymerEngine {
+    pairs: Vec<(char, char)>,
+    index: HashMap<(char, char), usize>,
+    /// Indices of the pairs each pair becomes.
+    successors: Vec<Vec<usize>>,
+}
+
+impl PolymerEngine {
+    /// Compiles the rules for every pair which can grow from the polymer.
+    fn new(polymer: &Polymer, rules: &Ruleset, missing: MissingRule) -> Result<Self, String> {
+        let mut engine = PolymerEngine {
+            pairs: Vec::new(),
+            index: HashMap::new(),
+            successors: Vec::new(),
+        };
+        let mut initial: Vec<(char, char)> = polymer.pairs.keys().copied().collect();
+        initial.sort_unstable();
+        for pair in initial {
+            engine.intern(pair);
         }
+        // Interning appends new pairs, so this visits each reachable pair once
+        while engine.successors.len() < engine.pairs.len() {
+            let (left, right) = engine.pairs[engine.successors.len()];
+            let insertion = match (rules.get(&(left, right)), missing) {
+                (Some(insertion), _) => insertion.as_str(),
+                (None, MissingRule::PassThrough) => "",
+                (None, MissingRule::Error) => {
+                    return Err(format!("Ruleset does not contain key: {:?}", (left, right)))
+                }
+            };
+            // The pairs of the inserted run with the original ends around it
+            let run: Vec<char> = std::iter::once(left)
+                .chain(insertion.chars())
+                .chain(std::iter::once(right))
+                .collect();
+            let successors = run
+                .windows(2)
+                .map(|w| engine.intern((w[0], w[1])))
+                .collect();
+            engine.successors.push(successors);
+        }
+        Ok(engine)
+    }
+
+    fn intern(&mut self, pair: (char, char)) -> usize {
+        if let Some(&i) = self.index.get(&pair) {
+            return i;
+        }
+        self.pairs.push(pair);
+        self.index.insert(pair, self.pairs.len() - 1);
+        self.pairs.len() - 1
+    }
+
+    /// The step as a matrix, mapping pair counts to the next step's.
+    fn matrix<C: Count>(&self) -> Matrix<C> {
+        let mut m = Matrix::<C>::zero(self.pairs.len());
+        for (i, successors) in self.successors.iter().enumerate() {
+            for &j in successors {
+                m.set(j, i, m.get(j, i).add(&C::one()));
+            }
+        }
+        m
+    }
+
+    /// Counts of each pair, in the engine's order, after `steps` steps.
+    fn pair_counts<C: Count>(&self, polymer: &Polymer, steps: u64) -> Vec<C> {
+        let initial: Vec<C> = self
+            .pairs
+            .iter()
+            .map(|pair| C::from_u64(polymer.pairs.get(pair).copied().unwrap_or(0) as u64))
+            .collect();
+        self.matrix().pow_apply(steps, &initial)
     }
 
-    let first = polymer.first;
+    /// Counts of each element given the pair counts, sorted by element.
+    /// Every element but the first is the right half of one pair.
+    fn tally<C: Count>(&self, polymer: &Polymer, pair_counts: Vec<C>) -> Vec<(char, C)> {
+        let mut counts: BTreeMap<char, C> = BTreeMap::new();
+        counts.insert(polymer.first, C::one());
+        for (&(_, right), count) in self.pairs.iter().zip(pair_counts) {
+            let total = counts.entry(right).or_insert_with(C::zero);
+            *total = total.add(&count);
+        }
+        counts.into_iter().collect()
+    }
 
-    Ok(Polymer { pairs, first })
+    /// Everything countable about the polymer after `steps` steps. Which
+    /// elements and pairs appear is worked out separately from their counts,
+    /// as a count under a modulus can be zero for one that is present.
+    fn stats<C: Count + Ord>(&self, polymer: &Polymer, steps: u64) -> PolymerStats<C> {
+        let counts = self.pair_counts::<C>(polymer, steps);
+        let present = self.pair_counts::<Present>(polymer, steps);
+        let elements: Vec<(char, C)> = self
+            .tally(polymer, counts.clone())
+            .into_iter()
+            .zip(self.tally(polymer, present.clone()))
+            .filter(|(_, (_, present))| present.0)
+            .map(|(element, _)| element)
+            .collect();
+        let mut pairs: Vec<((char, char), C)> = self
+            .pairs
+            .iter()
+            .copied()
+            .zip(counts)
+            .zip(present)
+            .filter(|(_, present)| present.0)
+            .map(|(pair, _)| pair)
+            .collect();
+        pairs.sort_unstable_by_key(|&(pair, _)| pair);
+        PolymerStats {
+            length: elements
+                .iter()
+                .fold(C::zero(), |total, (_, count)| total.add(count)),
+            elements,
+            pairs,
+        }
+    }
 }
 
-fn score_polymer(polymer: Polymer) -> usize {
-    let mut cnts = HashMap::new();
+/// Whether something appears at all, as a count which saturates at one, so
+/// the step matrix also tells which pairs exist after any number of steps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Present(bool);
+
+impl Display for Present {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
 
-    for ((_l, r), &cnt) in polymer.pairs.iter() {
-        cnts.entry(r).and_modify(|e| *e += cnt).or_insert(cnt);
+impl Count for Present {
+    fn zero() -> Self {
+        Present(false)
     }
 
-    cnts.entry(&polymer.first)
-        .and_modify(|e| *e += 1)
-        .or_insert(1);
+    fn from_u64(n: u64) -> Self {
+        Present(n != 0)
+    }
 
-    if let Some((min, max)) = minimax(cnts.values()) {
-        max - min
-    } else {
-        panic!("Zero length polymer!");
+    fn add(&self, other: &Self) -> Self {
+        Present(self.0 || other.0)
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        Present(self.0 && other.0)
+    }
+}
+
+/// The most common element's count less the least common's.
+fn score_polymer(stats: &PolymerStats<u64>) -> Result<u64, String> {
+    match (stats.most_common(), stats.least_common()) {
+        (Some((_, max)), Some((_, min))) => Ok(max - min),
+        _ => Err("Zero length polymer!".to_string()),
     }
 }
 
-fn part1(polymer: Polymer, rules: &Ruleset) -> Result<usize, String> {
-    Ok(score_polymer(
-        (0..10).try_fold(polymer, |p, _| step_polymer(&p, rules))?,
-    ))
+fn part1(polymer: Polymer, rules: &Ruleset) -> Result<u64, String> {
+    let engine = PolymerEngine::new(&polymer, rules, MissingRule::Error)?;
+    score_polymer(&engine.stats(&polymer, 10))
 }
 
 // 3306 correct
 
-fn part2(polymer: Polymer, rules: &Ruleset) -> Result<usize, String> {
-    Ok(score_polymer(
-        (0..40).try_fold(polymer, |p, _| step_polymer(&p, rules))?,
-    ))
+fn part2(polymer: Polymer, rules: &Ruleset) -> Result<u64, String> {
+    let engine = PolymerEngine::new(&polymer, rules, MissingRule::Error)?;
+    score_polymer(&engine.stats(&polymer, 40))
 }
 
 // 3760312702877 correct
@@ -127,15 +353,221 @@ fn main() {
             .next()
             .expect("No polymer input provided.")
             .parse::<Polymer>()
-            .expect(format!("Failed to parse polymer input: \"{}\"", input).as_str());
+            .unwrap_or_else(|e| panic!("Failed to parse polymer input: \"{}\": {}", input, e));
+        let rules = parse_rules(lines.skip(1)).unwrap();
+        (polymer_input, rules)
+    };
+
+    println!("Part 1: {:?}", part1(polymer_input.clone(), &rules));
+    println!("Part 2: {:?}", part2(polymer_input.clone(), &rules));
+
+    // "<steps> [mod] [passthrough] [show]" prints the polymer's statistics
+    // after any number of steps, exactly or modulo 1e9+7, optionally leaving
+    // pairs without rules alone and printing the polymer itself if short.
+    // Exact counts double in size each step.
+    if let Some(steps) = args.get(2) {
+        let steps: u64 = steps.parse().expect("Steps must be a number");
+        let flag = |name: &str| args[3..].iter().any(|arg| arg == name);
+        let missing = if flag("passthrough") {
+            MissingRule::PassThrough
+        } else {
+            MissingRule::Error
+        };
+        let engine = match PolymerEngine::new(&polymer_input, &rules, missing) {
+            Ok(engine) => engine,
+            Err(e) => return println!("{}", e),
+        };
+        if flag("mod") {
+            print_stats(&engine.stats::<Mod<1_000_000_007>>(&polymer_input, steps));
+        } else {
+            print_stats(&engine.stats::<BigUint>(&polymer_input, steps));
+        }
+        if flag("show") {
+            match polymer_input.materialise(&rules, missing, steps, 1_000_000) {
+                Ok(polymer) => println!("{}", polymer),
+                Err(e) => println!("{}", e),
+            }
+        }
+    }
+}
+
+fn print_stats<C: Count + Ord>(stats: &PolymerStats<C>) {
+    println!("Length: {}", stats.length);
+    for (element, count) in &stats.elements {
+        println!("{}: {}", element, count);
+    }
+    for ((left, right), count) in &stats.pairs {
+        println!("{}{}: {}", left, right, count);
+    }
+    if let (Some(most), Some(least)) = (stats.most_common(), stats.least_common()) {
+        println!("Most common: {:?} ({})", most.0, most.1);
+        println!("Least common: {:?} ({})", least.0, least.1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> (Polymer, Ruleset) {
+        let mut lines = input.lines();
+        let polymer = lines.next().unwrap().parse().unwrap();
         let rules = lines
             .skip(1)
             .map(to_rule)
-            .collect::<Result<Ruleset, _>>()
+            .collect::<Result<_, _>>()
             .unwrap();
-        (polymer_input, rules)
-    };
+        (polymer, rules)
+    }
 
-    println!("Part 1: {:?}", part1(polymer_input.clone(), &rules));
-    println!("Part 2: {:?}", part2(polymer_input, &rules));
+    #[test]
+    fn test_example() {
+        let (polymer, rules) = parse(include_str!("../example1.txt"));
+        assert_eq!(part1(polymer.clone(), &rules), Ok(1588));
+        assert_eq!(part2(polymer, &rules), Ok(2188189693529));
+    }
+
+    #[test]
+    fn test_many_steps() {
+        let (polymer, rules) = parse(include_str!("../example1.txt"));
+        let engine = PolymerEngine::new(&polymer, &rules, MissingRule::Error).unwrap();
+        let exact: Vec<(char, String)> = engine
+            .stats::<BigUint>(&polymer, 100)
+            .elements
+            .into_iter()
+            .map(|(element, count)| (element, count.to_string()))
+            .collect();
+        assert_eq!(
+            exact[0],
+            ('B', "2535298019313139394077918899467".to_string())
+        );
+        assert_eq!(
+            exact[3],
+            ('N', "1267649032861687663532044327198".to_string())
+        );
+
+        let modular = engine
+            .stats::<Mod<1_000_000_007>>(&polymer, 1_000_000)
+            .elements;
+        assert_eq!(
+            modular,
+            vec![
+                ('B', Mod(622022412)),
+                ('C', Mod(317023047)),
+                ('H', Mod(60926660)),
+                ('N', Mod(705154066))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_missing_rule() {
+        let (polymer, mut rules) = parse(include_str!("../example1.txt"));
+        rules.remove(&('C', 'H'));
+        assert!(PolymerEngine::new(&polymer, &rules, MissingRule::Error).is_err());
+        assert!(PolymerEngine::new(&polymer, &rules, MissingRule::PassThrough).is_ok());
+    }
+
+    type Counts = (Vec<(char, u64)>, Vec<((char, char), u64)>);
+
+    /// Element and pair counts read straight off a polymer string.
+    fn count_string(polymer: &str) -> Counts {
+        let chars: Vec<char> = polymer.chars().collect();
+        let mut elements: BTreeMap<char, u64> = BTreeMap::new();
+        for &c in &chars {
+            *elements.entry(c).or_default() += 1;
+        }
+        let mut pairs: BTreeMap<(char, char), u64> = BTreeMap::new();
+        for w in chars.windows(2) {
+            *pairs.entry((w[0], w[1])).or_default() += 1;
+        }
+        (elements.into_iter().collect(), pairs.into_iter().collect())
+    }
+
+    #[test]
+    fn test_stats_match_polymer() {
+        let (polymer, rules) = parse(include_str!("../example1.txt"));
+        let engine = PolymerEngine::new(&polymer, &rules, MissingRule::Error).unwrap();
+        for steps in [0, 1, 4, 10] {
+            let stats = engine.stats::<u64>(&polymer, steps);
+            let string = polymer
+                .materialise(&rules, MissingRule::Error, steps, 1 << 20)
+                .unwrap();
+            assert_eq!(stats.length, string.len() as u64);
+            assert_eq!((stats.elements, stats.pairs), count_string(&string));
+        }
+        assert_eq!(
+            polymer.materialise(&rules, MissingRule::Error, 2, 100),
+            Ok("NBCCNBBBCBHCB".to_string())
+        );
+        assert!(polymer
+            .materialise(&rules, MissingRule::Error, 10, 1000)
+            .is_err());
+
+        let stats = engine.stats::<u64>(&polymer, 10);
+        assert_eq!(stats.most_common(), Some((vec!['B'], 1749)));
+        assert_eq!(stats.least_common(), Some((vec!['H'], 161)));
+    }
+
+    #[test]
+    fn test_counts_wrapping_to_zero() {
+        // Six As and five Bs, with five AA pairs
+        let polymer: Polymer = "AAAAAABBBBB".parse().unwrap();
+        let engine = PolymerEngine::new(&polymer, &Ruleset::new(), MissingRule::PassThrough);
+        let stats = engine.unwrap().stats::<Mod<5>>(&polymer, 2);
+        assert_eq!(stats.elements, vec![('A', Mod(1)), ('B', Mod(0))]);
+        assert_eq!(
+            stats.pairs,
+            vec![
+                (('A', 'A'), Mod(0)),
+                (('A', 'B'), Mod(1)),
+                (('B', 'B'), Mod(4))
+            ]
+        );
+        assert_eq!(stats.least_common(), Some((vec!['B'], Mod(0))));
+    }
+
+    #[test]
+    fn test_ties() {
+        let polymer: Polymer = "NNCB".parse().unwrap();
+        let engine = PolymerEngine::new(&polymer, &Ruleset::new(), MissingRule::PassThrough);
+        let stats = engine.unwrap().stats::<BigUint>(&polymer, 3);
+        let two = BigUint::from_u64(2);
+        assert_eq!(stats.most_common(), Some((vec!['N'], two)));
+        assert_eq!(stats.least_common(), Some((vec!['B', 'C'], BigUint::one())));
+        assert_eq!(stats.length, BigUint::from_u64(4));
+    }
+
+    #[test]
+    fn test_longer_insertions() {
+        // ABAB, then ABABABAB with every BA left alone
+        let polymer: Polymer = "AB".parse().unwrap();
+        let rules = parse_rules(["AB -> BA"].into_iter()).unwrap();
+        assert!(PolymerEngine::new(&polymer, &rules, MissingRule::Error).is_err());
+        let engine = PolymerEngine::new(&polymer, &rules, MissingRule::PassThrough).unwrap();
+        assert_eq!(
+            engine.stats::<u64>(&polymer, 1).elements,
+            vec![('A', 2), ('B', 2)]
+        );
+        assert_eq!(
+            engine.stats::<u64>(&polymer, 2).elements,
+            vec![('A', 4), ('B', 4)]
+        );
+
+        let rules = parse_rules(["AB -> xyz", "Ax -> ", "xy -> ", "yz -> ", "zB -> "].into_iter());
+        let engine = PolymerEngine::new(&polymer, &rules.unwrap(), MissingRule::Error).unwrap();
+        let counts = engine.stats::<u64>(&polymer, 5).elements;
+        assert_eq!(counts.iter().map(|&(_, n)| n).sum::<u64>(), 5);
+    }
+
+    #[test]
+    fn test_rule_validation() {
+        assert!(parse_rules(["CH -> B", "NN -> C", "CH -> B"].into_iter()).is_ok());
+        assert_eq!(
+            parse_rules(["CH -> B", "NN -> C", "CH -> N"].into_iter()),
+            Err("Rules 1 and 3 conflict: CH -> B and CH -> N".to_string())
+        );
+        assert!(parse_rules(["CHB -> B"].into_iter()).is_err());
+        assert!(parse_rules(["CH => B"].into_iter()).is_err());
+    }
 }
@@ -3,23 +3,26 @@ use std::str::FromStr;
 
 type Tile = u8;
 type Coord = usize;
+/// A cell as (x, y).
+type Point = (usize, usize);
 
 struct Map {
-    sidelen: usize,
+    width: usize,
+    height: usize,
     tiles: Vec<Tile>,
 }
 
 impl Display for Map {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        for y in 0..self.sidelen {
-            for x in 0..self.sidelen {
-                let tile = self.tiles[y * self.sidelen + x];
-                if tile < 1 || tile > 9 {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let tile = self.tiles[y * self.width + x];
+                if !(1..=9).contains(&tile) {
                     panic!("Invalid tile: {} at position {},{}", tile, x, y);
                 }
                 write!(f, "{} ", tile)?;
             }
-            write!(f, "\n")?;
+            writeln!(f)?;
         }
         Ok(())
     }
@@ -30,7 +33,8 @@ impl FromStr for Map {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut tiles: Vec<Tile> = Vec::new();
-        let mut sidelen = 0;
+        let mut width = 0;
+        let mut height = 0;
         for line in s.lines() {
             tiles.extend(
                 line.chars()
@@ -42,19 +46,24 @@ impl FromStr for Map {
                     .collect::<Result<Vec<_>, Self::Err>>()?,
             );
 
-            if sidelen == 0 {
-                sidelen = tiles.len();
-            } else if sidelen != line.len() {
+            if height == 0 {
+                width = tiles.len();
+            } else if width != line.len() {
                 return Err(format!(
                     "Invalid map: row has length {} but previous rows have length {}",
                     line.len(),
-                    sidelen
+                    width
                 ));
             }
+            height += 1;
+        }
+        if width == 0 {
+            return Err("Invalid map: no tiles".to_string());
         }
         Ok(Map {
-            sidelen: sidelen,
-            tiles: tiles,
+            width,
+            height,
+            tiles,
         })
     }
 }
@@ -82,8 +91,33 @@ impl PartialOrd for State {
 }
 
 impl Map {
-    fn shortest_path_cost(&self, start: usize, goal: usize) -> Result<usize, String> {
-        let mut dist = vec![std::usize::MAX; self.tiles.len()];
+    fn index(&self, (x, y): Point) -> Result<Coord, String> {
+        if x < self.width && y < self.height {
+            Ok(y * self.width + x)
+        } else {
+            Err(format!("{},{} is off the map", x, y))
+        }
+    }
+
+    fn neighbours(&self, position: Coord) -> impl Iterator<Item = Coord> {
+        let (x, y) = (position % self.width, position / self.width);
+        let width = self.width;
+        [
+            (x > 0).then(|| position - 1),
+            (x + 1 < self.width).then_some(position + 1),
+            (y > 0).then(|| position - width),
+            (y + 1 < self.height).then_some(position + width),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    /// The least total risk of the tiles entered on the way from `start` to
+    /// `goal`, and a path with that risk from `start` to `goal` inclusive.
+    fn shortest_path(&self, start: Point, goal: Point) -> Result<(usize, Vec<Point>), String> {
+        let (start, goal) = (self.index(start)?, self.index(goal)?);
+        let mut dist = vec![usize::MAX; self.tiles.len()];
+        let mut previous = vec![None; self.tiles.len()];
         let mut heap = std::collections::BinaryHeap::new();
 
         dist[start] = 0;
@@ -94,56 +128,32 @@ impl Map {
 
         while let Some(State { cost, position }) = heap.pop() {
             if position == goal {
-                return Ok(cost);
+                // Walk back along the cheapest arrivals
+                let mut path = vec![position];
+                while let Some(p) = previous[*path.last().unwrap()] {
+                    path.push(p);
+                }
+                let path = path
+                    .into_iter()
+                    .rev()
+                    .map(|p| (p % self.width, p / self.width))
+                    .collect();
+                return Ok((cost, path));
             }
 
             if dist[position] < cost {
                 continue;
             }
 
-            let x = position % self.sidelen;
             // Check whether we can now get to any neighbors at less cost
-            if x > 0 {
-                let left = position - 1;
-                let left_cost = dist[position] + self.tiles[left] as usize;
-                if dist[left] > left_cost {
-                    dist[left] = left_cost;
+            for next in self.neighbours(position) {
+                let next_cost = cost + self.tiles[next] as usize;
+                if dist[next] > next_cost {
+                    dist[next] = next_cost;
+                    previous[next] = Some(position);
                     heap.push(State {
-                        cost: left_cost,
-                        position: left,
-                    });
-                }
-            }
-            if x < self.sidelen - 1 {
-                let right = position + 1;
-                let right_cost = dist[position] + self.tiles[right] as usize;
-                if dist[right] > right_cost {
-                    dist[right] = right_cost;
-                    heap.push(State {
-                        cost: right_cost,
-                        position: right,
-                    });
-                }
-            }
-            if position >= self.sidelen {
-                let up = position - self.sidelen;
-                let up_cost = dist[position] + self.tiles[up] as usize;
-                if dist[up] > up_cost {
-                    dist[up] = up_cost;
-                    heap.push(State {
-                        cost: up_cost,
-                        position: up,
-                    });
-                }
-            }
-            let down = position + self.sidelen;
-            if down < self.tiles.len() {
-                let down_cost = dist[position] + self.tiles[down] as usize;
-                if dist[down] > down_cost {
-                    dist[down] = down_cost;
-                    heap.push(State {
-                        cost: down_cost,
-                        position: down,
+                        cost: next_cost,
+                        position: next,
                     });
                 }
             }
@@ -152,10 +162,15 @@ impl Map {
         Err("No path found.".to_string())
     }
 
+    /// The opposite corner from the top left.
+    fn bottom_right(&self) -> Point {
+        (self.width - 1, self.height - 1)
+    }
+
     fn inc_tile(self, sidemult: u8) -> Map {
         let mut newtiles = Vec::with_capacity(self.tiles.len() * (sidemult * sidemult) as usize);
         for y in 0..sidemult {
-            for line in self.tiles.chunks(self.sidelen) {
+            for line in self.tiles.chunks(self.width) {
                 for x in 0..sidemult {
                     newtiles.extend(line.iter().map(|&t| (t + x + y - 1) % 9 + 1));
                 }
@@ -163,22 +178,22 @@ impl Map {
         }
 
         Map {
-            sidelen: self.sidelen * sidemult as usize,
+            width: self.width * sidemult as usize,
+            height: self.height * sidemult as usize,
             tiles: newtiles,
         }
     }
 }
 
 fn part1(map: &Map) -> Result<usize, String> {
-    map.shortest_path_cost(0, map.tiles.len() - 1)
+    Ok(map.shortest_path((0, 0), map.bottom_right())?.0)
 }
 
 // 390 correct
 
 fn part2(map: Map) -> Result<usize, String> {
     let newmap = map.inc_tile(5);
-    println!("{}", newmap);
-    newmap.shortest_path_cost(0, newmap.tiles.len() - 1)
+    Ok(newmap.shortest_path((0, 0), newmap.bottom_right())?.0)
 }
 
 // 2388 too low because I was wrapping numbers one value too soon
@@ -194,5 +209,52 @@ fn main() {
         .unwrap();
 
     println!("Part 1: {}", part1(&contents).unwrap());
+
+    // Optionally route between any two cells, given as "x,y x,y"
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() > 3 {
+        let point = |arg: &str| -> Point {
+            let (x, y) = arg.split_once(',').expect("Give cells as x,y");
+            (x.parse().unwrap(), y.parse().unwrap())
+        };
+        match contents.shortest_path(point(&args[2]), point(&args[3])) {
+            Ok((cost, path)) => println!("Risk {} along {:?}", cost, path),
+            Err(e) => println!("{}", e),
+        }
+    }
+
     println!("Part 2: {}", part2(contents).unwrap());
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_example() {
+        let map: Map = include_str!("../example1.txt").parse().unwrap();
+        assert_eq!(part1(&map), Ok(40));
+        assert_eq!(part2(map), Ok(315));
+    }
+
+    #[test]
+    fn test_rectangular_paths() {
+        let map: Map = "1911\n1191\n9111".parse().unwrap();
+        assert_eq!((map.width, map.height), (4, 3));
+        let (cost, path) = map.shortest_path((0, 0), map.bottom_right()).unwrap();
+        assert_eq!(cost, 5);
+        assert_eq!(path, vec![(0, 0), (0, 1), (1, 1), (1, 2), (2, 2), (3, 2)]);
+
+        // Routes can run any way across the map
+        let (cost, path) = map.shortest_path((3, 0), (0, 2)).unwrap();
+        assert_eq!(cost, 13);
+        assert_eq!(path.first(), Some(&(3, 0)));
+        assert_eq!(path.last(), Some(&(0, 2)));
+        assert_eq!(map.shortest_path((2, 1), (2, 1)), Ok((0, vec![(2, 1)])));
+        assert!(map.shortest_path((0, 0), (4, 0)).is_err());
+
+        let tall = map.inc_tile(2);
+        assert_eq!((tall.width, tall.height), (8, 6));
+        assert!("12\n1".parse::<Map>().is_err());
+    }
+}
@@ -1,8 +1,11 @@
+use std::collections::HashMap;
 use std::fmt::Display;
 
 struct BingoCard {
     numbers: [[u8; 5]; 5],
     marked: [[bool; 5]; 5],
+    row_hits: [u8; 5],
+    col_hits: [u8; 5],
     winningnum: Option<u8>,
 }
 
@@ -30,7 +33,7 @@ impl Display for BingoCard {
 impl From<&[&str]> for BingoCard {
     fn from(s: &[&str]) -> Self {
         let numbers: Vec<[u8; 5]> = s
-            .into_iter()
+            .iter()
             .map(|line| {
                 let nums = line
                     .split_whitespace()
@@ -45,52 +48,35 @@ impl From<&[&str]> for BingoCard {
         BingoCard {
             numbers: [numbers[0], numbers[1], numbers[2], numbers[3], numbers[4]],
             marked: [[false; 5]; 5],
+            row_hits: [0; 5],
+            col_hits: [0; 5],
             winningnum: None,
         }
     }
 }
 
 trait Bingo {
-    fn mark(&mut self, num: u8);
-    fn won(&self) -> bool;
+    fn mark_cell(&mut self, row: usize, col: usize, num: u8) -> bool;
     fn score(&self) -> u32;
 }
 
 impl Bingo for BingoCard {
-    fn mark(&mut self, num: u8) {
-        let mut did_mark = false;
-        for i in 0..5 {
-            for j in 0..5 {
-                if self.numbers[i][j] == num {
-                    self.marked[i][j] = true;
-                    did_mark = true;
-                }
-            }
+    /// Marks the cell at `(row, col)`, which must hold `num`.
+    /// Returns true only on the call which completes this card's first line.
+    fn mark_cell(&mut self, row: usize, col: usize, num: u8) -> bool {
+        if self.marked[row][col] {
+            return false;
         }
+        self.marked[row][col] = true;
+        self.row_hits[row] += 1;
+        self.col_hits[col] += 1;
 
-        if did_mark && self.winningnum.is_none() && self.won() {
+        if self.winningnum.is_none() && (self.row_hits[row] == 5 || self.col_hits[col] == 5) {
             self.winningnum = Some(num);
-        }
-    }
-
-    fn won(&self) -> bool {
-        if self.winningnum.is_some() {
-            return true;
+            true
         } else {
-            for row in &self.marked {
-                if row.iter().all(|&x| x) {
-                    return true;
-                }
-            }
-
-            for col in 0..5 {
-                if self.marked.iter().all(|&row| row[col]) {
-                    return true;
-                }
-            }
+            false
         }
-
-        false
     }
 
     fn score(&self) -> u32 {
@@ -107,28 +93,78 @@ impl Bingo for BingoCard {
     }
 }
 
-fn part1(input: &str) -> u32 {
+/// A set of cards indexed by the numbers they hold, so that each draw only
+/// touches the cells it actually marks.
+struct BingoGame {
+    cards: Vec<BingoCard>,
+    positions: HashMap<u8, Vec<(usize, usize, usize)>>,
+    remaining: usize,
+}
+
+impl BingoGame {
+    fn new(cards: Vec<BingoCard>) -> Self {
+        let mut positions: HashMap<u8, Vec<(usize, usize, usize)>> = HashMap::new();
+        for (c, card) in cards.iter().enumerate() {
+            for (i, row) in card.numbers.iter().enumerate() {
+                for (j, &num) in row.iter().enumerate() {
+                    positions.entry(num).or_default().push((c, i, j));
+                }
+            }
+        }
+
+        BingoGame {
+            remaining: cards.len(),
+            cards,
+            positions,
+        }
+    }
+
+    /// Marks `num` everywhere it appears, returning the indices of the cards
+    /// which won on this draw, in card order.
+    fn draw(&mut self, num: u8) -> Vec<usize> {
+        let mut winners = Vec::new();
+        if let Some(cells) = self.positions.get(&num) {
+            for &(c, i, j) in cells {
+                if self.cards[c].mark_cell(i, j, num) {
+                    winners.push(c);
+                }
+            }
+        }
+        self.remaining -= winners.len();
+        winners
+    }
+
+    fn all_won(&self) -> bool {
+        self.remaining == 0
+    }
+}
+
+fn parse_input(input: &str) -> (Vec<u8>, BingoGame) {
     let mut inputlines = input.lines().filter(|&line| !line.is_empty());
     let inputseq = inputlines
         .next()
         .unwrap()
         .split(",")
-        .map(|s| s.parse::<u8>().unwrap());
+        .map(|s| s.parse::<u8>().unwrap())
+        .collect();
 
-    let mut cards: Vec<BingoCard> = inputlines
+    let cards: Vec<BingoCard> = inputlines
         .collect::<Vec<&str>>()
         .chunks(5)
         .map(|lines| lines.into())
         .collect();
 
+    (inputseq, BingoGame::new(cards))
+}
+
+fn part1(input: &str) -> u32 {
+    let (inputseq, mut game) = parse_input(input);
+
     for num in inputseq {
         // println!("Calling: {}", num);
-        for (_i, card) in (&mut cards).iter_mut().enumerate() {
-            card.mark(num);
-            if card.won() {
-                // println!("Winner: Card {}\n{}", _i + 1, card);
-                return card.score();
-            }
+        if let Some(&winner) = game.draw(num).first() {
+            // println!("Winner: Card {}\n{}", winner + 1, game.cards[winner]);
+            return game.cards[winner].score();
         }
     }
 
@@ -139,24 +175,13 @@ fn part1(input: &str) -> u32 {
 // 12796 correct
 
 fn part2(input: &str) -> u32 {
-    let mut inputlines = input.lines().filter(|&line| !line.is_empty());
-    let inputseq = inputlines
-        .next()
-        .unwrap()
-        .split(",")
-        .map(|s| s.parse::<u8>().unwrap());
-
-    let mut cards: Vec<BingoCard> = inputlines
-        .collect::<Vec<&str>>()
-        .chunks(5)
-        .map(|lines| lines.into())
-        .collect();
+    let (inputseq, mut game) = parse_input(input);
 
     for num in inputseq {
-        for i in 0..cards.len() {
-            cards[i].mark(num);
-            if cards.iter().all(|c| c.won()) {
-                return cards[i].score();
+        let winners = game.draw(num);
+        if game.all_won() {
+            if let Some(&last) = winners.last() {
+                return game.cards[last].score();
             }
         }
     }
@@ -169,3 +194,29 @@ fn main() {
     println!("Part 1: {}", part1(input));
     println!("Part 2: {}", part2(input));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_part1_example() {
+        assert_eq!(part1(include_str!("../example1.txt")), 4512);
+    }
+
+    #[test]
+    fn test_part2_example() {
+        assert_eq!(part2(include_str!("../example1.txt")), 1924);
+    }
+
+    #[test]
+    fn test_draw_reports_each_winner_once() {
+        let (inputseq, mut game) = parse_input(include_str!("../example1.txt"));
+        let mut winners = Vec::new();
+        for num in inputseq {
+            winners.extend(game.draw(num));
+        }
+        assert_eq!(winners, vec![2, 0, 1]);
+        assert!(game.all_won());
+    }
+}
@@ -1,8 +1,9 @@
+use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
 
-type Coord = u16;
+type Coord = i64;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 struct Point {
     x: Coord,
     y: Coord,
@@ -14,7 +15,7 @@ impl FromStr for Point {
         let v = s
             .split_once(',')
             .map(|(a, b)| (a.parse::<Coord>(), b.parse::<Coord>()))
-            .ok_or_else(|| "Insufficient input numbers")?;
+            .ok_or("Insufficient input numbers")?;
         if let (Ok(a), Ok(b)) = v {
             Ok(Point { x: a, y: b })
         } else {
@@ -27,12 +28,37 @@ impl FromStr for Point {
 enum Line {
     Horizontal(Coord, (Coord, Coord)),
     Vertical(Coord, (Coord, Coord)),
+    /// Covers only the lattice points lying exactly on the line.
     Arbitrary(Point, Point),
+    /// Covers every cell Bresenham's algorithm visits between the points.
+    Bresenham(Point, Point),
 }
 
-impl FromStr for Line {
+/// How lines which are neither horizontal nor vertical are rasterised.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Raster {
+    /// Only 45° lines are accepted, as the puzzle promises.
+    Diagonal,
+    /// Any slope, covering only the exact lattice points on the line.
+    Lattice,
+    /// Any slope, also covering the non-lattice cells the line passes through.
+    Bresenham,
+}
+
+impl FromStr for Raster {
     type Err = &'static str;
-    fn from_str(line: &str) -> Result<Self, Self::Err> {
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "diagonal" => Ok(Raster::Diagonal),
+            "lattice" => Ok(Raster::Lattice),
+            "bresenham" => Ok(Raster::Bresenham),
+            _ => Err("Raster mode must be one of diagonal, lattice or bresenham"),
+        }
+    }
+}
+
+impl Line {
+    fn parse_with(line: &str, raster: Raster) -> Result<Self, &'static str> {
         let unpacked = line
             .split(" -> ")
             .map(|s| s.parse::<Point>())
@@ -43,10 +69,15 @@ impl FromStr for Line {
             } else if a.y == b.y {
                 Ok(Line::Horizontal(a.y, (a.x.min(b.x), b.x.max(a.x))))
             } else {
-                Ok(Line::Arbitrary(
-                    if a.x < b.x { a } else { b },
-                    if a.x > b.x { a } else { b },
-                ))
+                let (a, b) = if a.x < b.x { (a, b) } else { (b, a) };
+                let diagonal = (b.x - a.x) == (b.y - a.y).abs();
+                match raster {
+                    Raster::Diagonal if !diagonal => {
+                        Err("Line is neither axis-aligned nor diagonal")
+                    }
+                    Raster::Bresenham if !diagonal => Ok(Line::Bresenham(a, b)),
+                    _ => Ok(Line::Arbitrary(a, b)),
+                }
             }
         } else {
             Err("Bleh")
@@ -54,79 +85,370 @@ impl FromStr for Line {
     }
 }
 
-fn gridsize(input: &[Line]) -> (Coord, Coord) {
-    let (x, y) = input
-        .into_iter()
-        .map(|l| match *l {
-            Line::Horizontal(y, (_x1, x2)) => (x2, y),
-            Line::Vertical(x, (_y1, y2)) => (x, y2),
-            Line::Arbitrary(a, b) => (a.x.max(b.x), a.y.max(b.y)),
+impl FromStr for Line {
+    type Err = &'static str;
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        Line::parse_with(line, Raster::Diagonal)
+    }
+}
+
+/// Parses one line per row, naming the first row `raster` cannot represent.
+fn parse_lines(input: &str, raster: Raster) -> Result<Vec<Line>, String> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(i, l)| {
+            Line::parse_with(l, raster).map_err(|e| format!("line {} \"{}\": {}", i + 1, l, e))
         })
-        .fold((0, 0), |(x, y), (x2, y2)| (x.max(x2), y.max(y2)));
+        .collect()
+}
 
-    (x + 1, y + 1)
+fn gcd(a: Coord, b: Coord) -> Coord {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
 }
 
-fn part1(input: &[Line], maxx: Coord, maxy: Coord) -> usize {
-    let mut grid = vec![vec![0u16; maxx as usize]; maxy as usize];
+impl Line {
+    fn endpoints(&self) -> (Point, Point) {
+        match *self {
+            Line::Horizontal(y, (x1, x2)) => (Point { x: x1, y }, Point { x: x2, y }),
+            Line::Vertical(x, (y1, y2)) => (Point { x, y: y1 }, Point { x, y: y2 }),
+            Line::Arbitrary(a, b) | Line::Bresenham(a, b) => (a, b),
+        }
+    }
+
+    /// For lines covering only lattice points: the first point, the step
+    /// between successive points and the number of steps to the last point.
+    fn walk(&self) -> Option<(Point, Point, Coord)> {
+        if let Line::Bresenham(_, _) = self {
+            return None;
+        }
+        let (a, b) = self.endpoints();
+        let n = gcd(b.x - a.x, b.y - a.y);
+        if n == 0 {
+            return Some((a, Point { x: 0, y: 0 }, 0));
+        }
+        let step = Point {
+            x: (b.x - a.x) / n,
+            y: (b.y - a.y) / n,
+        };
+        Some((a, step, n))
+    }
 
-    let input_no_arbitrary = input.into_iter().filter(|l| match l {
-        Line::Arbitrary(_, _) => false,
-        _ => true,
-    });
+    /// Every cell covered by the line, from its first point to its last.
+    fn points(&self) -> Vec<Point> {
+        if let Some((start, step, n)) = self.walk() {
+            return (0..=n)
+                .map(|k| Point {
+                    x: start.x + step.x * k,
+                    y: start.y + step.y * k,
+                })
+                .collect();
+        }
 
-    for line in input_no_arbitrary {
-        match *line {
-            Line::Horizontal(y, (x1, x2)) => {
-                for x in x1..=x2 {
-                    grid[y as usize][x as usize] += 1;
-                }
+        let (a, b) = self.endpoints();
+        let (dx, dy) = ((b.x - a.x).abs(), -(b.y - a.y).abs());
+        let (sx, sy) = ((b.x - a.x).signum(), (b.y - a.y).signum());
+        let mut err = dx + dy;
+        let mut p = a;
+        let mut points = vec![p];
+        while p != b {
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                p.x += sx;
             }
-            Line::Vertical(x, (y1, y2)) => {
-                for y in y1..=y2 {
-                    grid[y as usize][x as usize] += 1;
+            if e2 <= dx {
+                err += dx;
+                p.y += sy;
+            }
+            points.push(p);
+        }
+        points
+    }
+
+    fn covers(&self, p: Point) -> bool {
+        let (lo, hi) = self.bounds();
+        if p.x < lo.x || p.x > hi.x || p.y < lo.y || p.y > hi.y {
+            return false;
+        }
+        match self.walk() {
+            Some((start, step, _)) => {
+                let (dx, dy) = (p.x as i128 - start.x as i128, p.y as i128 - start.y as i128);
+                let step = (step.x as i128, step.y as i128);
+                // Inside the box and on the line, it is a lattice point iff it is a whole number of steps away
+                cross((dx, dy), step) == 0
+                    && (step.0 == 0 || dx % step.0 == 0)
+                    && (step.1 == 0 || dy % step.1 == 0)
+            }
+            None => {
+                // Each step of a shallow line moves one column and each step
+                // of a steep one moves one row, so the cell in any column
+                // (or row) is the line's exact position there, rounded half up.
+                let (a, b) = self.endpoints();
+                let (w, h) = (
+                    (b.x as i128 - a.x as i128).unsigned_abs(),
+                    (b.y as i128 - a.y as i128).unsigned_abs(),
+                );
+                let (i, j) = (
+                    (p.x as i128 - a.x as i128).unsigned_abs(),
+                    (p.y as i128 - a.y as i128).unsigned_abs(),
+                );
+                let round = |n: u128, d: u128| n / d + (2 * (n % d) >= d) as u128;
+                if w >= h {
+                    j == round(h * i, w)
+                } else {
+                    i == round(w * j, h)
                 }
             }
-            _ => panic!("Arbitrary line not expected in part 1"),
         }
     }
 
-    grid.into_iter().flatten().filter(|&x| x >= 2).count()
+    /// The number of steps from the line's first cell to its last.
+    fn steps(&self) -> u128 {
+        match self.walk() {
+            Some((_, _, n)) => n as u128,
+            None => {
+                let (a, b) = self.endpoints();
+                (b.x as i128 - a.x as i128)
+                    .unsigned_abs()
+                    .max((b.y as i128 - a.y as i128).unsigned_abs())
+            }
+        }
+    }
+
+    /// Bottom-left and top-right corners of the line's bounding box.
+    fn bounds(&self) -> (Point, Point) {
+        let (start, end) = self.endpoints();
+        (
+            Point {
+                x: start.x.min(end.x),
+                y: start.y.min(end.y),
+            },
+            Point {
+                x: start.x.max(end.x),
+                y: start.y.max(end.y),
+            },
+        )
+    }
 }
 
-// 4728 correct
+fn cross(a: (i128, i128), b: (i128, i128)) -> i128 {
+    a.0 * b.1 - a.1 * b.0
+}
 
-fn part2(input: &[Line], maxx: Coord, maxy: Coord) -> usize {
-    let mut grid = vec![vec![0u16; maxx as usize]; maxy as usize];
+/// All points covered by both lines.
+fn intersections(l1: &Line, l2: &Line) -> Vec<Point> {
+    let ((p, d1, n1), (q, d2, n2)) = match (l1.walk(), l2.walk()) {
+        (Some(w1), Some(w2)) => (w1, w2),
+        _ => {
+            // Bresenham cells don't follow a single step, so walk the shorter
+            // line and keep the cells the other covers.
+            let (short, long) = if l1.steps() <= l2.steps() {
+                (l1, l2)
+            } else {
+                (l2, l1)
+            };
+            return short
+                .points()
+                .into_iter()
+                .filter(|&p| long.covers(p))
+                .collect();
+        }
+    };
+    // A single point has no direction, so just check whether the other line covers it
+    if n1 == 0 {
+        return if l2.covers(p) { vec![p] } else { vec![] };
+    }
+    if n2 == 0 {
+        return if l1.covers(q) { vec![q] } else { vec![] };
+    }
+    let pq = ((q.x - p.x) as i128, (q.y - p.y) as i128);
+    let d1w = (d1.x as i128, d1.y as i128);
+    let d2w = (d2.x as i128, d2.y as i128);
+    let at = |k: i128| Point {
+        x: p.x + d1.x * k as Coord,
+        y: p.y + d1.y * k as Coord,
+    };
 
-    for &line in input {
-        match line {
-            Line::Horizontal(y, (x1, x2)) => {
-                for x in x1..=x2 {
-                    grid[y as usize][x as usize] += 1;
-                }
-            }
-            Line::Vertical(x, (y1, y2)) => {
-                for y in y1..=y2 {
-                    grid[y as usize][x as usize] += 1;
+    let denom = cross(d1w, d2w);
+    if denom == 0 {
+        if cross(pq, d1w) != 0 {
+            // Parallel but not collinear
+            return vec![];
+        }
+        // Both steps are primitive, so collinear lines step by +-d1 and q is
+        // a whole number of d1 steps from p.
+        let dot = |a: (i128, i128), b: (i128, i128)| a.0 * b.0 + a.1 * b.1;
+        let len = dot(d1w, d1w);
+        let t0 = dot(pq, d1w) / len;
+        let t1 = t0 + dot(d2w, d1w) / len * n2 as i128;
+        let lo = t0.min(t1).max(0);
+        let hi = t0.max(t1).min(n1 as i128);
+        (lo..=hi).map(at).collect()
+    } else {
+        let s = cross(pq, d2w);
+        let t = cross(pq, d1w);
+        if s % denom != 0 || t % denom != 0 {
+            return vec![];
+        }
+        let (s, t) = (s / denom, t / denom);
+        if (0..=n1 as i128).contains(&s) && (0..=n2 as i128).contains(&t) {
+            vec![at(s)]
+        } else {
+            vec![]
+        }
+    }
+}
+
+/// Every point covered by two or more lines, with the indices of the lines
+/// covering it. Found by sweeping the lines in order of their leftmost x and
+/// only intersecting those whose bounding boxes overlap, so memory is
+/// proportional to the number of overlaps rather than the size of the field.
+fn overlapping_lines<'a>(
+    input: impl IntoIterator<Item = (usize, &'a Line)>,
+) -> HashMap<Point, Vec<usize>> {
+    let mut lines: Vec<(Point, Point, usize, &Line)> = input
+        .into_iter()
+        .map(|(i, l)| {
+            let (lo, hi) = l.bounds();
+            (lo, hi, i, l)
+        })
+        .collect();
+    lines.sort_unstable_by_key(|&(lo, _, i, _)| (lo.x, i));
+
+    let mut overlaps: HashMap<Point, Vec<usize>> = HashMap::new();
+    let mut active: Vec<(Point, Point, usize, &Line)> = Vec::new();
+    for (lo, hi, i, line) in lines {
+        active.retain(|&(_, ahi, _, _)| ahi.x >= lo.x);
+        for &(alo, ahi, ai, aline) in &active {
+            if alo.y <= hi.y && lo.y <= ahi.y {
+                for p in intersections(aline, line) {
+                    overlaps.entry(p).or_default().extend([ai, i]);
                 }
             }
-            Line::Arbitrary(a, b) => {
-                // x values are always in order a -> b.
-                let mut y = a.y;
-                for x in a.x..=b.x {
-                    grid[y as usize][x as usize] += 1;
-                    if a.y > b.y {
-                        y -= 1;
-                    } else {
-                        y += 1;
-                    }
-                }
+        }
+        active.push((lo, hi, i, line));
+    }
+
+    for covering in overlaps.values_mut() {
+        covering.sort_unstable();
+        covering.dedup();
+    }
+    overlaps
+}
+
+/// Every point covered by two or more lines.
+fn overlap_points<'a>(input: impl IntoIterator<Item = &'a Line>) -> HashSet<Point> {
+    overlapping_lines(input.into_iter().enumerate())
+        .into_keys()
+        .collect()
+}
+
+/// Answers which lines cover which points of a vent field.
+struct VentField<'a> {
+    lines: &'a [Line],
+    overlaps: HashMap<Point, Vec<usize>>,
+}
+
+impl<'a> VentField<'a> {
+    fn new(lines: &'a [Line]) -> Self {
+        VentField {
+            lines,
+            overlaps: overlapping_lines(lines.iter().enumerate()),
+        }
+    }
+
+    /// Indices of the lines covering `p`, in ascending order.
+    fn lines_at(&self, p: Point) -> Vec<usize> {
+        match self.overlaps.get(&p) {
+            Some(covering) => covering.clone(),
+            // At most one line covers any point not in the overlap table
+            None => self
+                .lines
+                .iter()
+                .position(|l| l.covers(p))
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    fn count_at(&self, p: Point) -> usize {
+        self.lines_at(p).len()
+    }
+
+    /// All points covered by two or more lines with their line counts,
+    /// hottest first and ties in reading order.
+    fn hottest(&self) -> Vec<(Point, usize)> {
+        let mut ranked: Vec<(Point, usize)> = self
+            .overlaps
+            .iter()
+            .map(|(&p, covering)| (p, covering.len()))
+            .collect();
+        ranked.sort_unstable_by_key(|&(p, n)| (std::cmp::Reverse(n), p.y, p.x));
+        ranked
+    }
+
+    /// The whole field's bounding box drawn as in the puzzle text: `.` for
+    /// uncovered cells, the line count otherwise, and `+` above 9.
+    fn heatmap(&self) -> String {
+        let Some((lo, hi)) =
+            self.lines
+                .iter()
+                .map(|l| l.bounds())
+                .reduce(|(alo, ahi), (blo, bhi)| {
+                    (
+                        Point {
+                            x: alo.x.min(blo.x),
+                            y: alo.y.min(blo.y),
+                        },
+                        Point {
+                            x: ahi.x.max(bhi.x),
+                            y: ahi.y.max(bhi.y),
+                        },
+                    )
+                })
+        else {
+            return String::new();
+        };
+
+        let width = (hi.x - lo.x + 1) as usize;
+        let height = (hi.y - lo.y + 1) as usize;
+        let mut grid = vec![0usize; width * height];
+        for line in self.lines {
+            for p in line.points() {
+                grid[(p.y - lo.y) as usize * width + (p.x - lo.x) as usize] += 1;
             }
         }
+
+        let mut out = String::with_capacity((width + 1) * height);
+        for row in grid.chunks(width) {
+            out.extend(row.iter().map(|&n| match n {
+                0 => '.',
+                1..=9 => char::from_digit(n as u32, 10).unwrap(),
+                _ => '+',
+            }));
+            out.push('\n');
+        }
+        out
     }
+}
+
+fn part1(input: &[Line]) -> usize {
+    let input_no_arbitrary = input
+        .iter()
+        .filter(|l| matches!(l, Line::Horizontal(_, _) | Line::Vertical(_, _)));
+
+    overlap_points(input_no_arbitrary).len()
+}
+
+// 4728 correct
 
-    grid.into_iter().flatten().filter(|&x| x >= 2).count()
+fn part2(input: &[Line]) -> usize {
+    overlap_points(input).len()
 }
 
 // 6729 too low (wasn't iterating any points that went right-to-left or upward)
@@ -134,14 +456,211 @@ fn part2(input: &[Line], maxx: Coord, maxy: Coord) -> usize {
 // 17717 correct
 
 fn main() {
-    let input = include_str!("../input.txt")
-        .lines()
-        .map(|l| l.parse::<Line>())
-        .collect::<Result<Vec<Line>, _>>()
+    // Optionally choose how non-axis lines are drawn, defaulting to the puzzle's 45° lines
+    let raster = std::env::args()
+        .nth(1)
+        .map(|s| s.parse::<Raster>())
+        .unwrap_or(Ok(Raster::Diagonal))
         .unwrap();
+    let input = parse_lines(include_str!("../input.txt"), raster).unwrap();
+
+    println!("Part 1: {}", part1(&input));
+    println!("Part 2: {}", part2(&input));
+
+    let field = VentField::new(&input);
+    for (p, _) in field.hottest().into_iter().take(3) {
+        println!(
+            "{},{} covered {} times by lines {:?}",
+            p.x,
+            p.y,
+            field.count_at(p),
+            field.lines_at(p)
+        );
+    }
+
+    // Optionally export the full heatmap
+    if let Some(filename) = std::env::args().nth(2) {
+        std::fs::write(filename, field.heatmap())
+            .expect("Something went wrong writing the heatmap");
+    }
+}
 
-    let (x, y) = gridsize(&input);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    println!("Part 1: {}", part1(&input, x, y));
-    println!("Part 2: {}", part2(&input, x, y));
+    fn parse(input: &str) -> Vec<Line> {
+        parse_lines(input, Raster::Diagonal).unwrap()
+    }
+
+    #[test]
+    fn test_part1_example() {
+        assert_eq!(part1(&parse(include_str!("../example1.txt"))), 5);
+    }
+
+    #[test]
+    fn test_part2_example() {
+        assert_eq!(part2(&parse(include_str!("../example1.txt"))), 12);
+    }
+
+    #[test]
+    fn test_overlap_points_far_apart() {
+        let input = parse(
+            "-5000000000,7 -> 5000000000,7\n\
+             3,-9000000000 -> 3,9000000000\n\
+             -10,-3 -> 20,27\n\
+             4000000000,7 -> 4000000005,7",
+        );
+        let overlaps = overlap_points(&input);
+        let expected: HashSet<Point> = [(3, 7), (0, 7), (3, 10)]
+            .into_iter()
+            .map(|(x, y)| Point { x, y })
+            .chain((4000000000..=4000000005).map(|x| Point { x, y: 7 }))
+            .collect();
+        assert_eq!(overlaps, expected);
+    }
+
+    #[test]
+    fn test_point_lines() {
+        let input = parse("3,3 -> 3,3\n3,3 -> 3,3");
+        assert_eq!(part1(&input), 1);
+        assert_eq!(part2(&input), 1);
+
+        // The first point sweeps before the line through it, the second after
+        let input = parse("3,3 -> 3,3\n3,3 -> 8,3\n0,5 -> 6,5\n5,5 -> 5,5\n7,7 -> 7,7");
+        assert_eq!(
+            overlap_points(&input),
+            HashSet::from([Point { x: 3, y: 3 }, Point { x: 5, y: 5 }])
+        );
+        assert_eq!(part1(&input), 2);
+        assert_eq!(part2(&input), 2);
+    }
+
+    #[test]
+    fn test_lattice_far_apart() {
+        let input = parse_lines(
+            "0,0 -> 10000000000,9999999999\n\
+             9000000000,1 -> 9000000000,1\n\
+             -6000000000,-4000000000 -> 6000000000,4000000000\n\
+             3000000000,2000000000 -> 3000000000,2000000000",
+            Raster::Lattice,
+        )
+        .unwrap();
+        assert_eq!(
+            overlap_points(&input),
+            HashSet::from([
+                Point { x: 0, y: 0 },
+                Point {
+                    x: 3000000000,
+                    y: 2000000000
+                }
+            ])
+        );
+        let field = VentField::new(&input);
+        assert_eq!(
+            field.count_at(Point {
+                x: 9000000000,
+                y: 1
+            }),
+            1
+        );
+        assert_eq!(field.lines_at(Point { x: -3, y: -2 }), vec![2]);
+    }
+
+    #[test]
+    fn test_lattice_stepping() {
+        let line = Line::parse_with("6,4 -> 0,0", Raster::Lattice).unwrap();
+        assert_eq!(
+            line.points(),
+            vec![
+                Point { x: 0, y: 0 },
+                Point { x: 3, y: 2 },
+                Point { x: 6, y: 4 }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bresenham_counts_non_lattice_cells() {
+        let input = "0,0 -> 6,4\n0,1 -> 6,1";
+        let lattice = parse_lines(input, Raster::Lattice).unwrap();
+        assert!(overlap_points(&lattice).is_empty());
+
+        let bresenham = parse_lines(input, Raster::Bresenham).unwrap();
+        assert_eq!(bresenham[0].points().len(), 7);
+        assert_eq!(overlap_points(&bresenham).len(), 2);
+    }
+
+    #[test]
+    fn test_bresenham_coverage() {
+        for (x, y) in (-9..=9).flat_map(|x| (-9..=9).map(move |y| (x, y))) {
+            let Ok(line) = Line::parse_with(&format!("0,0 -> {},{}", x, y), Raster::Bresenham)
+            else {
+                continue;
+            };
+            let cells = line.points();
+            assert_eq!(cells.len() as u128, line.steps() + 1);
+            for p in (-9..=9).flat_map(|x| (-9..=9).map(move |y| Point { x, y })) {
+                assert_eq!(line.covers(p), cells.contains(&p), "{:?} {:?}", line, p);
+            }
+        }
+
+        // Only the short line's cells are walked
+        let input = parse_lines(
+            "0,0 -> 3,1\n-2000000000,0 -> 2000000000,0\n2,-2000000000 -> 2,2000000000",
+            Raster::Bresenham,
+        )
+        .unwrap();
+        assert_eq!(
+            intersections(&input[0], &input[1]),
+            vec![Point { x: 0, y: 0 }, Point { x: 1, y: 0 }]
+        );
+        assert_eq!(
+            intersections(&input[2], &input[0]),
+            vec![Point { x: 2, y: 1 }]
+        );
+        let field = VentField::new(&input);
+        assert_eq!(field.lines_at(Point { x: 3, y: 1 }), vec![0]);
+        assert_eq!(field.lines_at(Point { x: 2, y: 0 }), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_diagonal_rejects_other_slopes() {
+        let err = parse_lines("0,0 -> 2,2\n0,0 -> 6,4", Raster::Diagonal).unwrap_err();
+        assert!(err.starts_with("line 2 \"0,0 -> 6,4\""), "{}", err);
+    }
+
+    #[test]
+    fn test_example_heatmap() {
+        let input = parse(include_str!("../example1.txt"));
+        let field = VentField::new(&input);
+        assert_eq!(
+            field.heatmap(),
+            "1.1....11.\n\
+             .111...2..\n\
+             ..2.1.111.\n\
+             ...1.2.2..\n\
+             .112313211\n\
+             ...1.2....\n\
+             ..1...1...\n\
+             .1.....1..\n\
+             1.......1.\n\
+             222111....\n"
+        );
+    }
+
+    #[test]
+    fn test_point_attribution() {
+        let input = parse(include_str!("../example1.txt"));
+        let field = VentField::new(&input);
+        assert_eq!(field.lines_at(Point { x: 4, y: 4 }), vec![1, 2, 8]);
+        assert_eq!(field.lines_at(Point { x: 9, y: 4 }), vec![2]);
+        assert_eq!(field.count_at(Point { x: 9, y: 9 }), 0);
+
+        let hottest = field.hottest();
+        assert_eq!(hottest.len(), 12);
+        assert_eq!(hottest[0], (Point { x: 4, y: 4 }, 3));
+        assert_eq!(hottest[1], (Point { x: 6, y: 4 }, 3));
+        assert!(hottest[2..].iter().all(|&(_, n)| n == 2));
+    }
 }
@@ -1,45 +1,64 @@
-fn part1(input: &[u8]) -> usize {
-    let mut fish = [0usize; 9];
-    for &t in input {
-        fish[t as usize] += 1usize;
-    }
+use matpow::{BigUint, Count, Matrix};
 
-    println!("00: {:?}", fish);
+/// Lanternfish grouped by timer. Each day every timer counts down, and a
+/// fish at zero goes back to `reset` while spawning a new fish at `newborn`.
+struct Population<C> {
+    initial: Vec<C>,
+    transition: Matrix<C>,
+}
 
-    for _t in 1..=80 {
-        let mut new_fish = [0usize; 9];
-        for i in 0..8 {
-            new_fish[i] = fish[i + 1]
+impl<C: Count> Population<C> {
+    fn new(reset: usize, newborn: usize, fish: &[u8]) -> Result<Self, String> {
+        let longest = reset.max(newborn);
+        let mut counts = vec![0u64; longest + 1];
+        for &t in fish {
+            *counts.get_mut(t as usize).ok_or(format!(
+                "Fish timer {} exceeds the longest timer {}",
+                t, longest
+            ))? += 1;
         }
-        new_fish[8] = fish[0];
-        new_fish[6] += fish[0];
-        fish = new_fish;
-        println!("{:02}: {:?}", _t, fish);
-    }
 
-    fish.into_iter().sum()
-}
+        // new[i] = old[i + 1], new[newborn] += old[0], new[reset] += old[0]
+        let mut transition = Matrix::zero(longest + 1);
+        for i in 0..longest {
+            transition.set(i, i + 1, C::one());
+        }
+        transition.set(newborn, 0, C::one());
+        transition.set(reset, 0, transition.get(reset, 0).add(&C::one()));
 
-// 227214 too low
-// 372300 correct
+        Ok(Population {
+            initial: counts.into_iter().map(C::from_u64).collect(),
+            transition,
+        })
+    }
 
-fn part2(input: &[u8]) -> usize {
-    let mut fish = [0usize; 9];
-    for &t in input {
-        fish[t as usize] += 1usize;
+    /// The puzzle's rules: reset to 6 and newborns start at 8.
+    fn puzzle(fish: &[u8]) -> Result<Self, String> {
+        Self::new(6, 8, fish)
     }
 
-    for _t in 1..=256 {
-        let mut new_fish = [0usize; 9];
-        for i in 0..8 {
-            new_fish[i] = fish[i + 1]
-        }
-        new_fish[8] = fish[0];
-        new_fish[6] += fish[0];
-        fish = new_fish;
+    /// Number of fish at each timer value after `day` days.
+    fn counts_at(&self, day: u64) -> Vec<C> {
+        self.transition.pow_apply(day, &self.initial)
+    }
+
+    fn total_at(&self, day: u64) -> C {
+        self.counts_at(day)
+            .iter()
+            .fold(C::zero(), |acc, count| acc.add(count))
     }
+}
 
-    fish.into_iter().sum()
+fn part1(input: &[u8]) -> u64 {
+    let population = Population::<u64>::puzzle(input).unwrap();
+    population.total_at(80)
+}
+
+// 227214 too low
+// 372300 correct
+
+fn part2(input: &[u8]) -> u64 {
+    Population::<u64>::puzzle(input).unwrap().total_at(256)
 }
 
 // 1675781200288 correct
@@ -54,4 +73,59 @@ fn main() {
 
     println!("Part 1: {}", part1(&input));
     println!("Part 2: {}", part2(&input));
+
+    // Optionally count exactly for a much longer run
+    if let Some(days) = std::env::args().nth(1) {
+        let days = days.parse().expect("Day count must be a whole number");
+        let population = Population::<BigUint>::puzzle(&input).unwrap();
+        println!("Day {}: {}", days, population.total_at(days));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matpow::Mod;
+
+    const EXAMPLE: [u8; 5] = [3, 4, 3, 1, 2];
+
+    #[test]
+    fn test_example() {
+        let population = Population::<u64>::puzzle(&EXAMPLE).unwrap();
+        assert_eq!(population.total_at(18), 26);
+        assert_eq!(population.total_at(80), 5934);
+        assert_eq!(population.total_at(256), 26984457539);
+        assert_eq!(population.counts_at(1), vec![1, 1, 2, 1, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_big_and_modular_counts_agree() {
+        let exact = Population::<BigUint>::puzzle(&EXAMPLE)
+            .unwrap()
+            .total_at(1000)
+            .to_string();
+        let modular = Population::<Mod<1_000_000_007>>::puzzle(&EXAMPLE)
+            .unwrap()
+            .total_at(1000);
+        let reduced = exact.bytes().fold(0u64, |acc, d| {
+            (acc * 10 + (d - b'0') as u64) % 1_000_000_007
+        });
+        assert_eq!(reduced, modular.0);
+    }
+
+    #[test]
+    fn test_custom_timers() {
+        // Fish which spawn every day double daily
+        let population = Population::<u64>::new(0, 0, &[0, 0, 0]).unwrap();
+        assert_eq!(population.total_at(10), 3 * 1024);
+        assert!(Population::<u64>::new(0, 0, &[1]).is_err());
+
+        // Adults may restart above where newborns start
+        let population = Population::<u64>::new(3, 2, &[1]).unwrap();
+        assert_eq!(population.counts_at(2), vec![0, 0, 1, 1]);
+        assert_eq!(population.counts_at(5), vec![1, 0, 1, 1]);
+        assert_eq!(population.total_at(10), 8);
+        assert!(Population::<u64>::new(3, 2, &[3]).is_ok());
+        assert!(Population::<u64>::new(3, 2, &[4]).is_err());
+    }
 }
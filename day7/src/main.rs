@@ -1,36 +1,182 @@
-type Coord = u32;
-
-fn part1(input: &[Coord]) -> Coord {
-    let min = *(input.iter().min().unwrap());
-    let max = *(input.iter().max().unwrap());
-    (min..=max)
-        .map(|xtarget| {
-            input
-                .iter()
-                .map(|&xstart| Coord::max(xtarget, xstart) - Coord::min(xtarget, xstart))
+type Coord = i64;
+type Fuel = u128;
+type Weight = u64;
+
+/// The fuel a crab burns to move a given distance. Costs must be convex and
+/// non-decreasing in distance, so that total fuel is convex in the target.
+trait FuelCost {
+    fn cost(&self, distance: u64) -> Fuel;
+
+    /// Targets among which an optimum is known to lie, given the crabs'
+    /// weighted positions sorted by position. `None` falls back to a
+    /// ternary search.
+    fn candidates(&self, _sorted: &[(Coord, Weight)]) -> Option<Vec<Coord>> {
+        None
+    }
+}
+
+/// One unit of fuel per step.
+struct Linear;
+
+impl FuelCost for Linear {
+    fn cost(&self, distance: u64) -> Fuel {
+        distance as Fuel
+    }
+
+    fn candidates(&self, sorted: &[(Coord, Weight)]) -> Option<Vec<Coord>> {
+        // The lower weighted median minimises the sum of absolute distances
+        let total: Fuel = sorted.iter().map(|&(_, w)| w as Fuel).sum();
+        let mut seen = 0;
+        sorted
+            .iter()
+            .find(|&&(_, w)| {
+                seen += w as Fuel;
+                seen * 2 >= total
+            })
+            .map(|&(x, _)| vec![x])
+    }
+}
+
+/// Each step costs one more than the last.
+struct Triangular;
+
+impl FuelCost for Triangular {
+    fn cost(&self, distance: u64) -> Fuel {
+        let d = distance as Fuel;
+        d * (d + 1) / 2
+    }
+
+    fn candidates(&self, sorted: &[(Coord, Weight)]) -> Option<Vec<Coord>> {
+        // The optimum lies within half a step of the weighted mean
+        let total: i128 = sorted.iter().map(|&(_, w)| w as i128).sum();
+        if total == 0 {
+            return None;
+        }
+        let sum: i128 = sorted.iter().map(|&(x, w)| x as i128 * w as i128).sum();
+        let floor = sum.div_euclid(total) as Coord;
+        Some(vec![floor - 1, floor, floor + 1, floor + 2])
+    }
+}
+
+/// Where the crabs are allowed to meet.
+enum Constraint<const D: usize> {
+    Anywhere,
+    /// Inclusive bounds on each axis.
+    Within([(Coord, Coord); D]),
+    OneOf(Vec<[Coord; D]>),
+}
+
+struct Alignment<const D: usize> {
+    target: [Coord; D],
+    /// Fuel burnt by each crab, in input order.
+    fuel: Vec<Fuel>,
+    total: Fuel,
+}
+
+/// Fuel burnt along one axis by crabs at weighted positions.
+fn axis_fuel<F: FuelCost>(axis: &[(Coord, Weight)], cost: &F, target: Coord) -> Fuel {
+    axis.iter()
+        .map(|&(x, w)| w as Fuel * cost.cost(x.abs_diff(target)))
+        .sum()
+}
+
+/// The leftmost target on one axis costing the least fuel, clamped into
+/// `bounds` when given.
+fn align_axis<F: FuelCost>(
+    axis: &[(Coord, Weight)],
+    cost: &F,
+    bounds: Option<(Coord, Coord)>,
+) -> Option<Coord> {
+    let mut sorted = axis.to_vec();
+    sorted.sort_unstable();
+    let (min, max) = (sorted.first()?.0, sorted.last()?.0);
+
+    let candidates = cost.candidates(&sorted).unwrap_or_else(|| {
+        // Ternary search over the convex total, narrowing to a few targets
+        let (mut lo, mut hi) = (min, max);
+        while hi - lo > 2 {
+            let m1 = lo + (hi - lo) / 3;
+            let m2 = hi - (hi - lo) / 3;
+            if axis_fuel(axis, cost, m1) <= axis_fuel(axis, cost, m2) {
+                hi = m2;
+            } else {
+                lo = m1 + 1;
+            }
+        }
+        (lo..=hi).collect()
+    });
+
+    let best = candidates
+        .into_iter()
+        .map(|t| t.clamp(min, max))
+        .min_by_key(|&t| (axis_fuel(axis, cost, t), t))?;
+    // Convexity means the best in-bounds target is the nearest to the optimum
+    Some(match bounds {
+        Some((lo, hi)) => best.clamp(lo, hi),
+        None => best,
+    })
+}
+
+/// Finds where weighted crabs in `D` dimensions should meet. Fuel is
+/// Manhattan-separable: each crab burns `cost` along every axis, scaled by
+/// its weight. Ties prefer the lowest target.
+fn align_weighted<F: FuelCost, const D: usize>(
+    crabs: &[([Coord; D], Weight)],
+    cost: &F,
+    constraint: &Constraint<D>,
+) -> Option<Alignment<D>> {
+    let axes: Vec<Vec<(Coord, Weight)>> = (0..D)
+        .map(|d| crabs.iter().map(|&(p, w)| (p[d], w)).collect())
+        .collect();
+
+    let target = match constraint {
+        Constraint::Anywhere | Constraint::Within(_) => {
+            let mut target = [0; D];
+            for (d, axis) in axes.iter().enumerate() {
+                let bounds = match constraint {
+                    Constraint::Within(bounds) if bounds[d].0 <= bounds[d].1 => Some(bounds[d]),
+                    Constraint::Within(_) => return None,
+                    _ => None,
+                };
+                target[d] = align_axis(axis, cost, bounds)?;
+            }
+            target
+        }
+        Constraint::OneOf(allowed) => *allowed.iter().min_by_key(|&&t| {
+            let total: Fuel = (0..D).map(|d| axis_fuel(&axes[d], cost, t[d])).sum();
+            (total, t)
+        })?,
+    };
+
+    let fuel: Vec<Fuel> = crabs
+        .iter()
+        .map(|&(p, w)| {
+            (0..D)
+                .map(|d| w as Fuel * cost.cost(p[d].abs_diff(target[d])))
                 .sum()
         })
-        .min()
-        .unwrap()
+        .collect();
+    Some(Alignment {
+        target,
+        total: fuel.iter().sum(),
+        fuel,
+    })
+}
+
+/// The puzzle's case: equally weighted crabs on a line, meeting anywhere.
+fn align<F: FuelCost>(crabs: &[Coord], cost: &F) -> Option<Alignment<1>> {
+    let crabs: Vec<([Coord; 1], Weight)> = crabs.iter().map(|&x| ([x], 1)).collect();
+    align_weighted(&crabs, cost, &Constraint::Anywhere)
+}
+
+fn part1(input: &[Coord]) -> Alignment<1> {
+    align(input, &Linear).unwrap()
 }
 
 // 328187 correct
 
-fn part2(input: &[Coord]) -> Coord {
-    let min = *(input.iter().min().unwrap());
-    let max = *(input.iter().max().unwrap());
-    (min..=max)
-        .map(|xtarget| {
-            input
-                .iter()
-                .map(|&xstart| {
-                    let distance = Coord::max(xtarget, xstart) - Coord::min(xtarget, xstart);
-                    (distance * distance + distance) >> 1
-                })
-                .sum()
-        })
-        .min()
-        .unwrap()
+fn part2(input: &[Coord]) -> Alignment<1> {
+    align(input, &Triangular).unwrap()
 }
 
 // 91257582 correct
@@ -42,6 +188,138 @@ fn main() {
         .map(|x| x.parse().unwrap())
         .collect::<Vec<Coord>>();
 
-    println!("Part 1: {}", part1(&input));
-    println!("Part 2: {}", part2(&input));
+    for (part, alignment) in [(1, part1(&input)), (2, part2(&input))] {
+        println!(
+            "Part {}: {} (target {}, costliest crab {})",
+            part,
+            alignment.total,
+            alignment.target[0],
+            alignment.fuel.iter().max().unwrap()
+        );
+    }
+
+    // Optionally restrict the meeting point to a range "lo..hi" or a list "a,b,c"
+    if let Some(arg) = std::env::args().nth(1) {
+        let constraint = match arg.split_once("..") {
+            Some((lo, hi)) => Constraint::Within([(lo.parse().unwrap(), hi.parse().unwrap())]),
+            None => Constraint::OneOf(arg.split(',').map(|x| [x.parse().unwrap()]).collect()),
+        };
+        let crabs: Vec<([Coord; 1], Weight)> = input.iter().map(|&x| ([x], 1)).collect();
+        for (name, alignment) in [
+            ("Linear", align_weighted(&crabs, &Linear, &constraint)),
+            (
+                "Triangular",
+                align_weighted(&crabs, &Triangular, &constraint),
+            ),
+        ] {
+            match alignment {
+                Some(alignment) => println!(
+                    "{} constrained: {} (target {})",
+                    name, alignment.total, alignment.target[0]
+                ),
+                None => println!("{} constrained: no allowed target", name),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: [Coord; 10] = [16, 1, 2, 0, 4, 2, 7, 1, 2, 14];
+
+    struct Squared;
+
+    impl FuelCost for Squared {
+        fn cost(&self, distance: u64) -> Fuel {
+            (distance as Fuel).pow(2)
+        }
+    }
+
+    fn brute_force<F: FuelCost>(crabs: &[(Coord, Weight)], cost: &F) -> (Fuel, Coord) {
+        let min = crabs.iter().map(|&(x, _)| x).min().unwrap();
+        let max = crabs.iter().map(|&(x, _)| x).max().unwrap();
+        (min..=max)
+            .map(|t| (axis_fuel(crabs, cost, t), t))
+            .min()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_example() {
+        let linear = align(&EXAMPLE, &Linear).unwrap();
+        assert_eq!((linear.total, linear.target), (37, [2]));
+        assert_eq!(linear.fuel, vec![14, 1, 0, 2, 2, 0, 5, 1, 0, 12]);
+
+        let triangular = align(&EXAMPLE, &Triangular).unwrap();
+        assert_eq!((triangular.total, triangular.target), (168, [5]));
+        assert_eq!(triangular.fuel[0], 66);
+    }
+
+    #[test]
+    fn test_solvers_match_brute_force() {
+        let crabs = [-40, 3, 3, 1000, 17, 250, -7, 64, 64, 64, 999];
+        let weights = [1, 7, 2, 3, 1, 1, 9, 4, 1, 1, 20];
+        for weighted in [false, true] {
+            let axis: Vec<(Coord, Weight)> = crabs
+                .iter()
+                .zip(weights)
+                .map(|(&x, w)| (x, if weighted { w } else { 1 }))
+                .collect();
+            let line: Vec<([Coord; 1], Weight)> = axis.iter().map(|&(x, w)| ([x], w)).collect();
+            let anywhere = Constraint::Anywhere;
+            for (got, want) in [
+                (
+                    align_weighted(&line, &Linear, &anywhere),
+                    brute_force(&axis, &Linear),
+                ),
+                (
+                    align_weighted(&line, &Triangular, &anywhere),
+                    brute_force(&axis, &Triangular),
+                ),
+                (
+                    align_weighted(&line, &Squared, &anywhere),
+                    brute_force(&axis, &Squared),
+                ),
+            ] {
+                let got = got.unwrap();
+                assert_eq!((got.total, got.target[0]), want);
+            }
+        }
+    }
+
+    #[test]
+    fn test_large_positions() {
+        let crabs = [0, 4_000_000_000_000, 4_000_000_000_000];
+        let triangular = align(&crabs, &Triangular).unwrap();
+        assert_eq!(triangular.target, [2_666_666_666_667]);
+        assert!(align(&[], &Linear).is_none());
+    }
+
+    #[test]
+    fn test_weighted_plane() {
+        let crabs = [([0, 0], 1), ([10, 0], 1), ([10, 10], 3), ([0, 10], 1)];
+        let alignment = align_weighted(&crabs, &Linear, &Constraint::Anywhere).unwrap();
+        assert_eq!(alignment.target, [10, 10]);
+        assert_eq!(alignment.fuel, vec![20, 10, 0, 10]);
+
+        let boxed = Constraint::Within([(0, 4), (2, 8)]);
+        let alignment = align_weighted(&crabs, &Linear, &boxed).unwrap();
+        assert_eq!(alignment.target, [4, 8]);
+    }
+
+    #[test]
+    fn test_allowed_targets() {
+        let crabs = [([0, 0, 0], 2), ([6, 6, 6], 1)];
+        let allowed = Constraint::OneOf(vec![[6, 6, 5], [1, 1, 1], [0, 0, 9]]);
+        let alignment = align_weighted(&crabs, &Triangular, &allowed).unwrap();
+        assert_eq!(alignment.target, [1, 1, 1]);
+        assert_eq!(alignment.total, 2 * 3 + 3 * 15);
+
+        let line = Constraint::OneOf(vec![[9], [4]]);
+        let alignment = align_weighted(&[([0], 1), ([2], 1)], &Linear, &line).unwrap();
+        assert_eq!(alignment.target, [4]);
+        assert!(align_weighted(&[([0], 1)], &Linear, &Constraint::OneOf(vec![])).is_none());
+    }
 }
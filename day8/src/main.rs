@@ -1,20 +1,13 @@
 use std::{
-    fmt::Debug,
-    ops::{BitAnd, BitOr, Neg, Sub},
+    fmt::{Debug, Display},
+    ops::{BitAnd, BitOr, Sub},
 };
 
 fn part1(input: &[&str]) -> usize {
     input
-        .into_iter()
-        .map(|&s| s.split_whitespace().skip_while(|&s| s != "|").skip(1))
-        .flatten()
-        .filter(|s| match s.len() {
-            2 => true,
-            3 => true,
-            4 => true,
-            7 => true,
-            _ => false,
-        })
+        .iter()
+        .flat_map(|&s| s.split_whitespace().skip_while(|&s| s != "|").skip(1))
+        .filter(|s| matches!(s.len(), 2 | 3 | 4 | 7))
         .count()
 }
 
@@ -24,17 +17,19 @@ fn part1(input: &[&str]) -> usize {
 type Etype = String;
 
 #[derive(PartialEq, Eq, Clone, Copy)]
-struct Segbitmap(u8);
+struct Segbitmap(u32);
 
 impl Debug for Segbitmap {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:#09b}", self.0)
+        write!(f, "{:#b}", self.0)
     }
 }
 
-impl Default for Segbitmap {
-    fn default() -> Self {
-        Segbitmap(127u8)
+/// Draws the bits as seven-segment display segments, numbered as in the
+/// diagram above `DisplayDef::seven_segment`.
+impl Display for Segbitmap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", DisplayDef::seven_segment().render(&[*self]))
     }
 }
 
@@ -46,13 +41,6 @@ impl Sub for Segbitmap {
     }
 }
 
-impl Neg for Segbitmap {
-    type Output = Segbitmap;
-    fn neg(self) -> Segbitmap {
-        Segbitmap::default() - self
-    }
-}
-
 impl BitAnd for Segbitmap {
     type Output = Segbitmap;
     fn bitand(self, rhs: Self) -> Self::Output {
@@ -71,147 +59,592 @@ impl BitOr for Segbitmap {
     }
 }
 
-fn seglist_to_segbitmap(l: &str) -> Result<Segbitmap, Etype> {
-    let v: Vec<u8> = l.chars().map(|c: char| c as u8 - 'a' as u8).collect();
-    if !v.iter().all(|&x| x < 8u8) {
-        Err("Character encountered greater than 'g'.".to_string())
-    } else {
-        Ok(Segbitmap(
-            v.into_iter().map(|x| 1 << x).fold(0u8, u8::bitor),
-        ))
+impl Segbitmap {
+    fn has(self, bit: usize) -> bool {
+        self.0 & (1 << bit) != 0
     }
 }
 
+/// Parses wire letters, where a display with `segments` wires uses the
+/// letters from 'a' onwards.
+fn seglist_to_segbitmap(l: &str, segments: usize) -> Result<Segbitmap, Etype> {
+    l.chars().try_fold(Segbitmap(0), |acc, c| {
+        let wire = (c as u32).wrapping_sub('a' as u32);
+        if (wire as usize) < segments {
+            Ok(acc | Segbitmap(1 << wire))
+        } else {
+            Err(format!(
+                "Character '{}' is not one of the {} wire letters.",
+                c, segments
+            ))
+        }
+    })
+}
+
+fn segbitmap_to_seglist(s: Segbitmap) -> String {
+    (0..32u8)
+        .filter(|&w| s.has(w as usize))
+        .map(|w| (b'a' + w) as char)
+        .collect()
+}
+
 fn displayseglist_to_displayidarr<const CNT: usize>(l: &str) -> Result<[Segbitmap; CNT], Etype> {
-    let mut ret = [Segbitmap::default(); CNT];
+    let mut ret = [Segbitmap(0); CNT];
     let xx: Vec<Segbitmap> = l
         .split_ascii_whitespace()
-        .map(seglist_to_segbitmap)
+        .map(|p| seglist_to_segbitmap(p, 7))
         .collect::<Result<Vec<Segbitmap>, Etype>>()?;
     if xx.len() < CNT {
-        return Err("Not enough display entries.".to_string());
+        Err("Not enough display entries.".to_string())
     } else if xx.len() > CNT {
-        return Err("Too many display entries.".to_string());
+        Err("Too many display entries.".to_string())
     } else {
-        for i in 0..CNT {
-            ret[i] = xx[i];
-        }
+        ret.copy_from_slice(&xx);
         Ok(ret)
     }
 }
 
-type DisplayData = (
-    Segbitmap,
-    Segbitmap,
-    Segbitmap,
-    [Segbitmap; 3],
-    [Segbitmap; 3],
-    Segbitmap,
-);
-
-fn displayseglist_to_displaysegcats(l: &str) -> Result<DisplayData, Etype> {
-    let mut displayidarr: [Segbitmap; 10] = displayseglist_to_displayidarr(l)?;
-
-    displayidarr.sort_unstable_by(|&a, &b| a.0.count_ones().cmp(&b.0.count_ones()));
-
-    Ok((
-        displayidarr[0], // 1
-        displayidarr[1], // 7
-        displayidarr[2], // 4
-        displayidarr[3..6]
-            .try_into()
-            .map_err(|_| "Slice to array conversion error.")?, // 5 segs
-        displayidarr[6..9]
-            .try_into()
-            .map_err(|_| "Slice to array conversion error.")?, // 6 segs
-        displayidarr[9], // 8
-    ))
-}
-
-//  0000
-// 5    1
-// 5    1
-//  6666
-// 4    2
-// 4    2
-//  3333
-
-// display 1 -> 2 segments
-// display 7 -> 3 segments
-// display 4 -> 4 segments
-// display 2 -> 5 segments
-// display 3 -> 5 segments
-// display 5 -> 5 segments
-// display 6 -> 6 segments
-// display 0 -> 6 segments
-// display 9 -> 6 segments
-// display 8 -> 7 segments
-
-fn gen_alphabet(insegchars: &str) -> Result<[Segbitmap; 10], Etype> {
-    let (one, seven, four, fivesegs, sixsegs, _) = displayseglist_to_displaysegcats(insegchars)?;
-
-    // println!("{:?}", (one, seven, four, fivesegs, sixsegs, eight));
-
-    let segs_036 = fivesegs
-        .iter()
-        .fold(Segbitmap::default(), |acc, &x| acc & x);
-    let segs_0523 = sixsegs.iter().fold(Segbitmap::default(), |acc, &x| acc & x);
-
-    let disp: [Segbitmap; 7] = [
-        seven - one,                  // 0
-        one - segs_0523,              // 1
-        one - (one - segs_0523),      // 2
-        segs_036 & (-(four | seven)), // 3
-        -(four | segs_036),           // 4
-        segs_0523 - (one | segs_036), // 5
-        four & segs_036,              // 6
-    ];
-
-    // println!("{:?}", disp);
-
-    Ok([
-        -disp[6],                       // 0
-        disp[1] | disp[2],              // 1
-        -(disp[5] | disp[2]),           // 2
-        -(disp[5] | disp[4]),           // 3
-        -(disp[0] | disp[3] | disp[4]), // 4
-        -(disp[1] | disp[4]),           // 5
-        -disp[1],                       // 6
-        disp[0] | disp[1] | disp[2],    // 7
-        Segbitmap::default(),           // 8
-        -disp[4],                       // 9
-    ])
+/// A display: how many segments it has, which segments each symbol lights,
+/// and where each segment is drawn when rendering.
+struct DisplayDef {
+    segments: usize,
+    glyphs: Vec<(char, Segbitmap)>,
+    /// Rows of drawing cells, each naming the segment and character lit there.
+    cells: Vec<Vec<Option<(usize, char)>>>,
 }
 
-fn solveline(input: &str) -> Result<usize, Etype> {
-    if let Some((insegchars, outsegchars)) = input.split_once(" | ") {
-        let outsegs: [Segbitmap; 4] = displayseglist_to_displayidarr(outsegchars)?;
-        let alphabet = gen_alphabet(insegchars)?;
-
-        Ok(outsegs
-            .into_iter()
-            .map(|x| {
-                alphabet
+fn segs(list: &[usize]) -> Segbitmap {
+    Segbitmap(list.iter().fold(0, |acc, &s| acc | (1 << s)))
+}
+
+impl DisplayDef {
+    //  0000
+    // 5    1
+    // 5    1
+    //  6666
+    // 4    2
+    // 4    2
+    //  3333
+
+    // display 1 -> 2 segments
+    // display 7 -> 3 segments
+    // display 4 -> 4 segments
+    // display 2 -> 5 segments
+    // display 3 -> 5 segments
+    // display 5 -> 5 segments
+    // display 6 -> 6 segments
+    // display 0 -> 6 segments
+    // display 9 -> 6 segments
+    // display 8 -> 7 segments
+
+    fn seven_segment() -> Self {
+        DisplayDef {
+            segments: 7,
+            glyphs: vec![
+                ('0', segs(&[0, 1, 2, 3, 4, 5])),
+                ('1', segs(&[1, 2])),
+                ('2', segs(&[0, 1, 3, 4, 6])),
+                ('3', segs(&[0, 1, 2, 3, 6])),
+                ('4', segs(&[1, 2, 5, 6])),
+                ('5', segs(&[0, 2, 3, 5, 6])),
+                ('6', segs(&[0, 2, 3, 4, 5, 6])),
+                ('7', segs(&[0, 1, 2])),
+                ('8', segs(&[0, 1, 2, 3, 4, 5, 6])),
+                ('9', segs(&[0, 1, 2, 3, 5, 6])),
+            ],
+            cells: vec![
+                vec![None, Some((0, '-')), None],
+                vec![Some((5, '|')), None, Some((1, '|'))],
+                vec![None, Some((6, '-')), None],
+                vec![Some((4, '|')), None, Some((2, '|'))],
+                vec![None, Some((3, '-')), None],
+            ],
+        }
+    }
+
+    //  00000
+    // 58 9 A1
+    // 5 89A 1
+    //  66 77
+    // 4 BCD 2
+    // 4B C D2
+    //  33333
+
+    /// An alphanumeric display: the seven segments above plus a split
+    /// middle bar (6 and 7) and upper and lower diagonals and verticals
+    /// (8 to 13).
+    fn fourteen_segment() -> Self {
+        DisplayDef {
+            segments: 14,
+            glyphs: vec![
+                ('0', segs(&[0, 1, 2, 3, 4, 5, 10, 11])),
+                ('1', segs(&[1, 2, 10])),
+                ('2', segs(&[0, 1, 3, 4, 6, 7])),
+                ('3', segs(&[0, 1, 2, 3, 7])),
+                ('4', segs(&[1, 2, 5, 6, 7])),
+                ('5', segs(&[0, 2, 3, 5, 6, 7])),
+                ('6', segs(&[0, 2, 3, 4, 5, 6, 7])),
+                ('7', segs(&[0, 1, 2])),
+                ('8', segs(&[0, 1, 2, 3, 4, 5, 6, 7])),
+                ('9', segs(&[0, 1, 2, 3, 5, 6, 7])),
+                ('A', segs(&[0, 1, 2, 4, 5, 6, 7])),
+                ('B', segs(&[0, 1, 2, 3, 7, 9, 12])),
+                ('C', segs(&[0, 3, 4, 5])),
+                ('D', segs(&[0, 1, 2, 3, 9, 12])),
+                ('E', segs(&[0, 3, 4, 5, 6])),
+                ('F', segs(&[0, 4, 5, 6])),
+                ('G', segs(&[0, 2, 3, 4, 5, 7])),
+                ('H', segs(&[1, 2, 4, 5, 6, 7])),
+                ('I', segs(&[0, 3, 9, 12])),
+                ('J', segs(&[1, 2, 3, 4])),
+                ('K', segs(&[4, 5, 6, 10, 13])),
+                ('L', segs(&[3, 4, 5])),
+                ('M', segs(&[1, 2, 4, 5, 8, 10])),
+                ('N', segs(&[1, 2, 4, 5, 8, 13])),
+                ('O', segs(&[0, 1, 2, 3, 4, 5])),
+                ('P', segs(&[0, 1, 4, 5, 6, 7])),
+                ('Q', segs(&[0, 1, 2, 3, 4, 5, 13])),
+                ('R', segs(&[0, 1, 4, 5, 6, 7, 13])),
+                ('S', segs(&[0, 2, 3, 7, 8])),
+                ('T', segs(&[0, 9, 12])),
+                ('U', segs(&[1, 2, 3, 4, 5])),
+                ('V', segs(&[4, 5, 10, 11])),
+                ('W', segs(&[1, 2, 4, 5, 11, 13])),
+                ('X', segs(&[8, 10, 11, 13])),
+                ('Y', segs(&[8, 10, 12])),
+                ('Z', segs(&[0, 3, 10, 11])),
+            ],
+            cells: vec![
+                vec![None, Some((0, '-')), Some((0, '-')), Some((0, '-')), None],
+                vec![
+                    Some((5, '|')),
+                    Some((8, '\\')),
+                    Some((9, '|')),
+                    Some((10, '/')),
+                    Some((1, '|')),
+                ],
+                vec![None, Some((6, '-')), None, Some((7, '-')), None],
+                vec![
+                    Some((4, '|')),
+                    Some((11, '/')),
+                    Some((12, '|')),
+                    Some((13, '\\')),
+                    Some((2, '|')),
+                ],
+                vec![None, Some((3, '-')), Some((3, '-')), Some((3, '-')), None],
+            ],
+        }
+    }
+
+    fn full(&self) -> Segbitmap {
+        Segbitmap((1 << self.segments) - 1)
+    }
+
+    /// Draws each set of segments as a glyph, side by side.
+    fn render(&self, glyphs: &[Segbitmap]) -> String {
+        self.cells
+            .iter()
+            .map(|row| {
+                glyphs
                     .iter()
-                    .position(|&y| x == y)
-                    .ok_or(format!("Outseg {:?} not in alphabet {:?}.", x, alphabet))
+                    .map(|g| {
+                        row.iter()
+                            .map(|cell| match cell {
+                                Some((seg, c)) if g.has(*seg) => *c,
+                                _ => ' ',
+                            })
+                            .collect::<String>()
+                    })
+                    .collect::<Vec<String>>()
+                    .join(" ")
             })
-            .collect::<Result<Vec<usize>, Etype>>()?
-            .into_iter()
-            .fold(0, |acc, x| acc * 10 + x))
-    } else {
-        Err("Bad delimiter.".to_string())
+            .collect::<Vec<String>>()
+            .join("\n")
     }
 }
 
+/// The display segment driven by each wire, indexed by wire.
+type Wiring = Vec<usize>;
+
+#[derive(Debug, PartialEq, Eq)]
+enum Wirings {
+    Unique(Wiring),
+    /// Every wiring consistent with the observations, in lexicographic order.
+    Ambiguous(Vec<Wiring>),
+}
+
+/// The segments lit when the given wires are driven.
+fn wire_segments(wiring: &Wiring, wires: Segbitmap) -> Segbitmap {
+    Segbitmap(
+        (0..wiring.len())
+            .filter(|&w| wires.has(w))
+            .fold(0, |acc, w| acc | (1 << wiring[w])),
+    )
+}
+
+/// The wire pattern shown for each of the display's symbols under `wiring`.
+fn wiring_alphabet(display: &DisplayDef, wiring: &Wiring) -> Vec<(char, Segbitmap)> {
+    display
+        .glyphs
+        .iter()
+        .map(|&(c, glyph)| {
+            let wires = (0..wiring.len())
+                .filter(|&w| glyph.has(wiring[w]))
+                .fold(0, |acc, w| acc | (1 << w));
+            (c, Segbitmap(wires))
+        })
+        .collect()
+}
+
+/// Finds every wiring under which all `observed` patterns show some glyph.
+/// Each pattern first narrows the segments its wires (and the wires it
+/// leaves dark) could drive. The search then assigns one wire at a time,
+/// dropping any glyph a pattern can no longer show and backing out as soon
+/// as a pattern has none left.
+fn solve_wiring(display: &DisplayDef, observed: &[Segbitmap]) -> Result<Wirings, Etype> {
+    let n = display.segments;
+    let mut observed = observed.to_vec();
+    observed.sort_unstable_by_key(|p| p.0);
+    observed.dedup();
+
+    let options: Vec<Vec<Segbitmap>> = observed
+        .iter()
+        .map(|p| {
+            display
+                .glyphs
+                .iter()
+                .map(|&(_, g)| g)
+                .filter(|g| g.0.count_ones() == p.0.count_ones())
+                .collect()
+        })
+        .collect();
+
+    let mut candidates = vec![display.full(); n];
+    for (&pattern, glyphs) in observed.iter().zip(&options) {
+        let lit = glyphs.iter().fold(Segbitmap(0), |acc, &g| acc | g);
+        let dark = glyphs
+            .iter()
+            .fold(Segbitmap(0), |acc, &g| acc | (display.full() - g));
+        for (wire, cands) in candidates.iter_mut().enumerate() {
+            if pattern.has(wire) {
+                *cands = *cands & lit;
+            } else {
+                *cands = *cands & dark;
+            }
+        }
+    }
+
+    // A wire pinned to one segment rules that segment out for the rest
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for wire in 0..n {
+            if candidates[wire].0.count_ones() == 1 {
+                for other in (0..n).filter(|&o| o != wire) {
+                    let narrowed = candidates[other] - candidates[wire];
+                    if narrowed != candidates[other] {
+                        candidates[other] = narrowed;
+                        changed = true;
+                    }
+                }
+            }
+        }
+    }
+
+    fn search(
+        wiring: &mut Wiring,
+        used: Segbitmap,
+        candidates: &[Segbitmap],
+        observed: &[Segbitmap],
+        options: &[Vec<Segbitmap>],
+        found: &mut Vec<Wiring>,
+    ) {
+        let wire = wiring.len();
+        if wire == candidates.len() {
+            found.push(wiring.clone());
+            return;
+        }
+        for seg in 0..candidates.len() {
+            if !(candidates[wire] - used).has(seg) {
+                continue;
+            }
+            // Keep the glyphs which agree with this wire being lit or dark
+            let narrowed: Vec<Vec<Segbitmap>> = observed
+                .iter()
+                .zip(options)
+                .map(|(p, glyphs)| {
+                    glyphs
+                        .iter()
+                        .copied()
+                        .filter(|g| p.has(wire) == g.has(seg))
+                        .collect()
+                })
+                .collect();
+            if narrowed.iter().any(|glyphs| glyphs.is_empty()) {
+                continue;
+            }
+            wiring.push(seg);
+            search(
+                wiring,
+                used | Segbitmap(1 << seg),
+                candidates,
+                observed,
+                &narrowed,
+                found,
+            );
+            wiring.pop();
+        }
+    }
+
+    let mut found = Vec::new();
+    search(
+        &mut Vec::with_capacity(n),
+        Segbitmap(0),
+        &candidates,
+        &observed,
+        &options,
+        &mut found,
+    );
+    match found.len() {
+        0 => Err("No wiring is consistent with the observed patterns.".to_string()),
+        1 => Ok(Wirings::Unique(found.pop().unwrap())),
+        _ => Ok(Wirings::Ambiguous(found)),
+    }
+}
+
+/// The puzzle line a display wired as `wiring` would produce while showing
+/// `text`: every glyph's pattern, then one pattern per symbol of `text`.
+fn encode_line(display: &DisplayDef, wiring: &Wiring, text: &str) -> Result<String, Etype> {
+    let alphabet = wiring_alphabet(display, wiring);
+    let patterns: Vec<String> = alphabet
+        .iter()
+        .map(|&(_, p)| segbitmap_to_seglist(p))
+        .collect();
+    let outputs = text
+        .chars()
+        .map(|c| {
+            alphabet
+                .iter()
+                .find(|&&(a, _)| a == c)
+                .map(|&(_, p)| segbitmap_to_seglist(p))
+                .ok_or(format!("No glyph for '{}'.", c))
+        })
+        .collect::<Result<Vec<String>, Etype>>()?;
+    Ok(format!("{} | {}", patterns.join(" "), outputs.join(" ")))
+}
+
+fn decode(alphabet: &[(char, Segbitmap)], outsegs: &[Segbitmap]) -> Result<String, Etype> {
+    outsegs
+        .iter()
+        .map(|&x| {
+            alphabet
+                .iter()
+                .find(|&&(_, y)| x == y)
+                .map(|&(c, _)| c)
+                .ok_or(format!("Outseg {:?} not in alphabet {:?}.", x, alphabet))
+        })
+        .collect()
+}
+
+/// Unscrambles the output half of a puzzle-format line for any display.
+fn solve_display_line(display: &DisplayDef, input: &str) -> Result<String, Etype> {
+    let (insegchars, outsegchars) = input.split_once(" | ").ok_or("Bad delimiter.")?;
+    let parse = |l: &str| {
+        l.split_ascii_whitespace()
+            .map(|p| seglist_to_segbitmap(p, display.segments))
+            .collect::<Result<Vec<Segbitmap>, Etype>>()
+    };
+    let outsegs = parse(outsegchars)?;
+    let mut observed = parse(insegchars)?;
+    observed.extend(&outsegs);
+
+    match solve_wiring(display, &observed)? {
+        Wirings::Unique(wiring) => decode(&wiring_alphabet(display, &wiring), &outsegs),
+        Wirings::Ambiguous(wirings) => {
+            // Still fine if every candidate reads the output the same way
+            let readings = wirings
+                .iter()
+                .map(|w| decode(&wiring_alphabet(display, w), &outsegs))
+                .collect::<Result<Vec<String>, Etype>>()?;
+            if readings.iter().all(|r| *r == readings[0]) {
+                Ok(readings[0].clone())
+            } else {
+                Err(format!(
+                    "{} wirings fit \"{}\", reading it as {:?}.",
+                    wirings.len(),
+                    input,
+                    readings
+                ))
+            }
+        }
+    }
+}
+
+fn solveline(input: &str) -> Result<usize, Etype> {
+    // The puzzle always shows four output digits
+    let (_, outsegchars) = input.split_once(" | ").ok_or("Bad delimiter.")?;
+    displayseglist_to_displayidarr::<4>(outsegchars)?;
+    solve_display_line(&DisplayDef::seven_segment(), input)?
+        .parse()
+        .map_err(|e| format!("{}", e))
+}
+
 fn part2(input: &[&str]) -> Result<usize, Etype> {
-    input.into_iter().map(|&s| solveline(s)).sum()
+    input.iter().map(|&s| solveline(s)).sum()
 }
 
 // 1063760 correct
 
 fn main() {
+    // `encode <wires> <text> [14]` scrambles text instead, where <wires> names
+    // the wire driving each segment in the order of the display's diagram
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() >= 4 && args[1] == "encode" {
+        let display = match args.get(4).map(|s| s.as_str()) {
+            Some("14") => DisplayDef::fourteen_segment(),
+            _ => DisplayDef::seven_segment(),
+        };
+        let mut wiring: Wiring = vec![0; display.segments];
+        for (seg, w) in args[2].bytes().enumerate() {
+            wiring[(w - b'a') as usize] = seg;
+        }
+        let line = encode_line(&display, &wiring, &args[3]).unwrap();
+        println!("{}", line);
+        let (_, outputs) = line.split_once(" | ").unwrap();
+        let outsegs: Vec<Segbitmap> = outputs
+            .split_whitespace()
+            .map(|p| wire_segments(&wiring, seglist_to_segbitmap(p, display.segments).unwrap()))
+            .collect();
+        println!("{}", display.render(&outsegs));
+        println!("{:?}", solve_display_line(&display, &line));
+        return;
+    }
+
     let input: Vec<&str> = include_str!("../input.txt").lines().collect();
     println!("{}", part1(input.as_slice()));
     println!("{:?}", part2(input.as_slice()));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(l: &str) -> Vec<Segbitmap> {
+        l.split_whitespace()
+            .map(|p| seglist_to_segbitmap(p, 7).unwrap())
+            .collect()
+    }
+
+    fn seven(observed: &[Segbitmap]) -> Result<Wirings, Etype> {
+        solve_wiring(&DisplayDef::seven_segment(), observed)
+    }
+
+    #[test]
+    fn test_example() {
+        let input: Vec<&str> = include_str!("../example1.txt").lines().collect();
+        assert_eq!(part1(&input), 26);
+        assert_eq!(part2(&input), Ok(61229));
+    }
+
+    #[test]
+    fn test_unique_wiring() {
+        let observed = patterns("acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab");
+        assert_eq!(
+            seven(&observed),
+            Ok(Wirings::Unique(vec![1, 2, 3, 0, 5, 6, 4]))
+        );
+        assert_eq!(
+            solveline("acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf"),
+            Ok(5353)
+        );
+    }
+
+    #[test]
+    fn test_partial_observations() {
+        // Only a 1 and a 7: the pair can swap and the other four wires are free
+        match seven(&patterns("ab dab")) {
+            Ok(Wirings::Ambiguous(wirings)) => {
+                assert_eq!(wirings.len(), 2 * 24);
+                assert!(wirings.iter().all(|w| w[3] == 0));
+            }
+            other => panic!("Expected ambiguity, got {:?}", other),
+        }
+
+        // Missing digits are fine as long as the rest pin the wiring down
+        let observed = patterns("acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb cagedb");
+        assert_eq!(
+            seven(&observed),
+            Ok(Wirings::Unique(vec![1, 2, 3, 0, 5, 6, 4]))
+        );
+        // Without the 7, nothing tells the top from the bottom
+        let observed = patterns("acedgfb cdfbe gcdfa fbcad cefabd cdfgeb cagedb");
+        assert_eq!(
+            seven(&observed),
+            Ok(Wirings::Ambiguous(vec![
+                vec![1, 2, 0, 3, 5, 6, 4],
+                vec![1, 2, 3, 0, 5, 6, 4]
+            ]))
+        );
+
+        assert!(seven(&patterns("ab ac")).is_err());
+    }
+
+    #[test]
+    fn test_encode_round_trip() {
+        let display = DisplayDef::seven_segment();
+        let wiring = vec![1, 2, 3, 0, 5, 6, 4];
+        let line = encode_line(&display, &wiring, "5353").unwrap();
+        assert_eq!(
+            line,
+            "abcdeg ab acdfg abcdf abef bcdef bcdefg abd abcdefg abcdef | bcdef abcdf bcdef abcdf"
+        );
+        assert_eq!(solveline(&line), Ok(5353));
+        assert_eq!(
+            solveline(&encode_line(&display, &wiring, "0007").unwrap()),
+            Ok(7)
+        );
+        assert!(encode_line(&display, &wiring, "A").is_err());
+    }
+
+    #[test]
+    fn test_render_glyphs() {
+        let display = DisplayDef::seven_segment();
+        let two = display.glyphs[2].1;
+        let seven = display.glyphs[7].1;
+        assert_eq!(
+            display.render(&[two, seven]),
+            " -   - \n  |   |\n -     \n|     |\n -     "
+        );
+        assert_eq!(display.glyphs[1].1.to_string(), "   \n  |\n   \n  |\n   ");
+    }
+
+    #[test]
+    fn test_fourteen_segment() {
+        let display = DisplayDef::fourteen_segment();
+        let mut glyphs: Vec<u32> = display.glyphs.iter().map(|&(_, g)| g.0).collect();
+        glyphs.sort_unstable();
+        glyphs.dedup();
+        assert_eq!(
+            glyphs.len(),
+            display.glyphs.len(),
+            "Glyphs must be distinct"
+        );
+
+        // Scramble by reversing the wires
+        let wiring: Wiring = (0..14).rev().collect();
+        let line = encode_line(&display, &wiring, "HELLO").unwrap();
+        assert_eq!(solve_display_line(&display, &line), Ok("HELLO".to_string()));
+        assert_eq!(
+            solve_wiring(&display, &patterns_for(&display, &line)),
+            Ok(Wirings::Unique(wiring))
+        );
+        assert!(seglist_to_segbitmap("o", 14).is_err());
+    }
+
+    fn patterns_for(display: &DisplayDef, line: &str) -> Vec<Segbitmap> {
+        line.split_whitespace()
+            .filter(|&p| p != "|")
+            .map(|p| seglist_to_segbitmap(p, display.segments).unwrap())
+            .collect()
+    }
+}
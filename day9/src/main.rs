@@ -1,66 +1,182 @@
-use std::collections::HashMap;
+use std::fmt::Display;
 
 type EType = String;
 type MyResult<T> = Result<T, EType>;
 
-fn is_low_point(input: &[Vec<u8>], y: usize, x: usize) -> bool {
-    let lower_than_above = y < 1 || input[y][x] < input[y - 1][x];
-    let lower_than_left = x < 1 || input[y][x] < input[y][x - 1];
-    let lower_than_right = x >= input.len() - 1 || input[y][x] < input[y][x + 1];
-    let lower_than_below = y >= input.len() - 1 || input[y][x] < input[y + 1][x];
-    lower_than_above && lower_than_left && lower_than_right && lower_than_below
+/// Which cells count as adjacent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Connectivity {
+    /// Up, down, left and right.
+    Four,
+    /// Also the four diagonals.
+    Eight,
+}
+
+/// Whether a low point must be strictly lower than its neighbours, or only
+/// no higher than them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Minimum {
+    Strict,
+    NonStrict,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Rules {
+    connectivity: Connectivity,
+    minimum: Minimum,
+    /// Cells this high or higher separate basins.
+    wall: u8,
+}
+
+impl Default for Rules {
+    /// The puzzle's rules.
+    fn default() -> Self {
+        Rules {
+            connectivity: Connectivity::Four,
+            minimum: Minimum::Strict,
+            wall: 9,
+        }
+    }
+}
+
+/// The in-bounds neighbours of (x, y) as (x, y).
+fn neighbours(
+    input: &[Vec<u8>],
+    x: usize,
+    y: usize,
+    connectivity: Connectivity,
+) -> impl Iterator<Item = (usize, usize)> + '_ {
+    const FOUR: [(isize, isize); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+    const DIAGONALS: [(isize, isize); 4] = [(-1, -1), (1, -1), (-1, 1), (1, 1)];
+    let diagonals = match connectivity {
+        Connectivity::Four => &DIAGONALS[..0],
+        Connectivity::Eight => &DIAGONALS[..],
+    };
+    FOUR.iter()
+        .chain(diagonals)
+        .map(move |&(dx, dy)| (x.wrapping_add_signed(dx), y.wrapping_add_signed(dy)))
+        .filter(|&(nx, ny)| input.get(ny).is_some_and(|row| nx < row.len()))
+}
+
+fn is_low_point(input: &[Vec<u8>], y: usize, x: usize, rules: &Rules) -> bool {
+    let h = input[y][x];
+    neighbours(input, x, y, rules.connectivity).all(|(nx, ny)| match rules.minimum {
+        Minimum::Strict => h < input[ny][nx],
+        Minimum::NonStrict => h <= input[ny][nx],
+    })
+}
+
+/// Every low point as (x, y), in reading order.
+fn low_points(input: &[Vec<u8>], rules: &Rules) -> Vec<(usize, usize)> {
+    (0..input.len())
+        .flat_map(|y| (0..input[y].len()).map(move |x| (x, y)))
+        .filter(|&(x, y)| is_low_point(input, y, x, rules))
+        .collect()
+}
+
+fn risk_level(input: &[Vec<u8>], rules: &Rules) -> u32 {
+    low_points(input, rules)
+        .into_iter()
+        .map(|(x, y)| input[y][x] as u32 + 1)
+        .sum()
 }
 
 fn part1(input: &[Vec<u8>]) -> u32 {
-    let mut total_risk: u32 = 0;
-    for y in 0..input.len() {
-        for x in 0..input[0].len() {
-            if is_low_point(input, y, x) {
-                total_risk += input[y][x] as u32 + 1u32;
+    risk_level(input, &Rules::default())
+}
+
+// 494 correct
+
+#[derive(Debug, PartialEq, Eq)]
+struct Basin {
+    size: usize,
+    /// The lowest cell as (x, y), first in reading order on ties.
+    low_point: (usize, usize),
+    /// Inclusive top-left and bottom-right corners as (x, y).
+    bounds: ((usize, usize), (usize, usize)),
+}
+
+struct BasinMap {
+    /// The basin id of each cell, or `None` for walls.
+    labels: Vec<Vec<Option<usize>>>,
+    /// Basins by id, numbered in reading order of their first cell.
+    basins: Vec<Basin>,
+}
+
+/// Draws walls as `#` and each basin as a letter, cycling through the alphabet.
+impl Display for BasinMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for row in &self.labels {
+            for label in row {
+                match label {
+                    Some(id) => write!(f, "{}", (b'a' + (id % 26) as u8) as char)?,
+                    None => write!(f, "#")?,
+                }
             }
+            writeln!(f)?;
         }
+        Ok(())
     }
-    total_risk
 }
 
-// 494 correct
+/// Labels each connected region of cells lower than the wall height as a basin.
+fn label_basins(input: &[Vec<u8>], rules: &Rules) -> BasinMap {
+    let wall = rules.wall;
+    let mut labels: Vec<Vec<Option<usize>>> =
+        input.iter().map(|row| vec![None; row.len()]).collect();
+    let mut basins = Vec::new();
 
-fn part2(input: &[Vec<u8>]) -> usize {
-    let mut basins: HashMap<(usize, usize), usize> = HashMap::new();
-    (0..input.len())
-        .map(|y| (0..input[0].len()).map(move |x| (x, y)))
-        .flatten()
-        .filter(|(x, y)| input[*y][*x] < 9)
-        .map(|(mut x, mut y)| {
-            while !is_low_point(input, y, x) {
-                if x < input[0].len() - 1 && input[y][x] > input[y][x + 1] {
-                    x += 1;
-                } else if y < input.len() - 1 && input[y][x] > input[y + 1][x] {
-                    y += 1;
-                } else if x > 0 && input[y][x] > input[y][x - 1] {
-                    x -= 1;
-                } else if y > 0 && input[y][x] > input[y - 1][x] {
-                    y -= 1;
+    for y in 0..input.len() {
+        for x in 0..input[y].len() {
+            if input[y][x] >= wall || labels[y][x].is_some() {
+                continue;
+            }
+
+            let id = basins.len();
+            let mut basin = Basin {
+                size: 0,
+                low_point: (x, y),
+                bounds: ((x, y), (x, y)),
+            };
+            labels[y][x] = Some(id);
+            let mut stack = vec![(x, y)];
+            while let Some((cx, cy)) = stack.pop() {
+                basin.size += 1;
+                let (lx, ly) = basin.low_point;
+                if (input[cy][cx], cy, cx) < (input[ly][lx], ly, lx) {
+                    basin.low_point = (cx, cy);
+                }
+                let ((x0, y0), (x1, y1)) = basin.bounds;
+                basin.bounds = ((x0.min(cx), y0.min(cy)), (x1.max(cx), y1.max(cy)));
+
+                for (nx, ny) in neighbours(input, cx, cy, rules.connectivity) {
+                    if input[ny][nx] < wall && labels[ny][nx].is_none() {
+                        labels[ny][nx] = Some(id);
+                        stack.push((nx, ny));
+                    }
                 }
             }
-            (x, y)
-        })
-        .for_each(|pt| {
-            let pt_entry = basins.entry(pt).or_insert(0);
-            *pt_entry += 1;
-        });
+            basins.push(basin);
+        }
+    }
 
-    let mut vs: Vec<usize> = basins.values().map(|x| *x).collect();
+    BasinMap { labels, basins }
+}
+
+fn part2(input: &[Vec<u8>]) -> usize {
+    let mut vs: Vec<usize> = label_basins(input, &Rules::default())
+        .basins
+        .iter()
+        .map(|b| b.size)
+        .collect();
     vs.sort_unstable();
-    vs[(vs.len() - 3)..]
-        .into_iter()
-        .fold(1usize, |acc, &x| acc * x)
+    vs[(vs.len() - 3)..].iter().product()
 }
 
 // 1048128 correct
 
-fn main() {
-    let input = include_str!("../input.txt")
+fn parse(input: &str) -> MyResult<Vec<Vec<u8>>> {
+    let grid = input
         .lines()
         .map(|l| {
             l.chars()
@@ -71,9 +187,115 @@ fn main() {
                 })
                 .collect::<MyResult<Vec<u8>>>()
         })
-        .collect::<MyResult<Vec<Vec<u8>>>>()
-        .unwrap();
+        .collect::<MyResult<Vec<Vec<u8>>>>()?;
+    if grid.iter().any(|row| row.len() != grid[0].len()) {
+        return Err("Heightmap rows differ in width".to_string());
+    }
+    Ok(grid)
+}
+
+fn main() {
+    let input = parse(include_str!("../input.txt")).unwrap();
 
     println!("Part 1: {}", part1(&input));
     println!("Part 2: {}", part2(&input));
+
+    // Optional flags: "diagonal" for 8-connectivity, "non-strict" for
+    // lower-or-equal minima and "map" to draw the basins
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut rules = Rules::default();
+    for arg in &args {
+        match arg.as_str() {
+            "diagonal" => rules.connectivity = Connectivity::Eight,
+            "non-strict" => rules.minimum = Minimum::NonStrict,
+            _ => {}
+        }
+    }
+    if args.iter().any(|arg| arg != "map") {
+        println!("Risk level: {}", risk_level(&input, &rules));
+        println!("Basins: {}", label_basins(&input, &rules).basins.len());
+    }
+    if args.iter().any(|arg| arg == "map") {
+        print!("{}", label_basins(&input, &rules));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_example() {
+        let input = parse(include_str!("../example1.txt")).unwrap();
+        assert_eq!(part1(&input), 15);
+        assert_eq!(part2(&input), 1134);
+    }
+
+    #[test]
+    fn test_basin_details() {
+        let input = parse(include_str!("../example1.txt")).unwrap();
+        let map = label_basins(&input, &Rules::default());
+        assert_eq!(map.basins.len(), 4);
+        assert_eq!(
+            map.basins[0],
+            Basin {
+                size: 3,
+                low_point: (1, 0),
+                bounds: ((0, 0), (1, 1)),
+            }
+        );
+        assert_eq!(map.basins[1].low_point, (9, 0));
+        assert_eq!(map.labels[0][2], None);
+        assert_eq!(map.labels[2][2], Some(2));
+        assert_eq!(map.labels[4][9], Some(3));
+    }
+
+    #[test]
+    fn test_flat_regions_and_walls() {
+        let input = parse("5575\n5755\n7555").unwrap();
+        let map = label_basins(&input, &Rules::default());
+        assert_eq!(map.basins.len(), 1);
+        assert_eq!(map.basins[0].size, 12);
+        assert_eq!(map.basins[0].low_point, (0, 0));
+
+        let rules = Rules {
+            wall: 7,
+            ..Default::default()
+        };
+        let map = label_basins(&input, &rules);
+        assert_eq!(map.basins.len(), 2);
+        assert_eq!(map.basins[0].size, 3);
+        assert_eq!(map.basins[1].size, 6);
+        assert_eq!(map.labels[2][3], Some(1));
+        assert_eq!(map.to_string(), "aa#b\na#bb\n#bbb\n");
+    }
+
+    #[test]
+    fn test_connectivity_and_minimum() {
+        let input = parse("919\n191\n919").unwrap();
+        let four = Rules::default();
+        assert_eq!(
+            low_points(&input, &four),
+            vec![(1, 0), (0, 1), (2, 1), (1, 2)]
+        );
+        assert_eq!(label_basins(&input, &four).basins.len(), 4);
+
+        let eight = Rules {
+            connectivity: Connectivity::Eight,
+            ..Default::default()
+        };
+        assert_eq!(low_points(&input, &eight), vec![]);
+        assert_eq!(label_basins(&input, &eight).basins.len(), 1);
+
+        let plateau = parse("3345\n3356").unwrap();
+        assert_eq!(risk_level(&plateau, &four), 0);
+        let non_strict = Rules {
+            minimum: Minimum::NonStrict,
+            ..Default::default()
+        };
+        assert_eq!(low_points(&plateau, &non_strict).len(), 4);
+        assert_eq!(risk_level(&plateau, &non_strict), 16);
+
+        assert!(parse("123\n12").is_err());
+    }
 }
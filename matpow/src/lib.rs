@@ -0,0 +1,321 @@
+use std::cmp::Ordering;
+use std::fmt::{self, Display};
+
+/// Something which can be counted with: `u64` panics on overflow, `Mod`
+/// wraps at a fixed modulus and `BigUint` never overflows.
+pub trait Count: Clone + Display {
+    fn zero() -> Self;
+    fn from_u64(n: u64) -> Self;
+    fn add(&self, other: &Self) -> Self;
+    fn mul(&self, other: &Self) -> Self;
+
+    fn one() -> Self {
+        Self::from_u64(1)
+    }
+}
+
+impl Count for u64 {
+    fn zero() -> Self {
+        0
+    }
+
+    fn from_u64(n: u64) -> Self {
+        n
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        self.checked_add(*other).expect("Count overflowed u64")
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        self.checked_mul(*other).expect("Count overflowed u64")
+    }
+}
+
+/// A count modulo `M`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Mod<const M: u64>(pub u64);
+
+impl<const M: u64> Display for Mod<M> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl<const M: u64> Count for Mod<M> {
+    fn zero() -> Self {
+        Mod(0)
+    }
+
+    fn from_u64(n: u64) -> Self {
+        Mod(n % M)
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        Mod(((self.0 as u128 + other.0 as u128) % M as u128) as u64)
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        Mod(((self.0 as u128 * other.0 as u128) % M as u128) as u64)
+    }
+}
+
+/// An arbitrary-precision unsigned integer, stored as little-endian base 2^32
+/// limbs with no trailing zero limbs.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BigUint {
+    limbs: Vec<u32>,
+}
+
+impl BigUint {
+    fn trim(mut self) -> Self {
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+        self
+    }
+
+    /// Divides in place by a small divisor, returning the remainder.
+    fn divmod_small(&mut self, divisor: u32) -> u32 {
+        let mut rem = 0u64;
+        for limb in self.limbs.iter_mut().rev() {
+            let cur = (rem << 32) | *limb as u64;
+            *limb = (cur / divisor as u64) as u32;
+            rem = cur % divisor as u64;
+        }
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+        rem as u32
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Without trailing zero limbs, more limbs means a larger number
+        self.limbs
+            .len()
+            .cmp(&other.limbs.len())
+            .then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.limbs.is_empty() {
+            return write!(f, "0");
+        }
+        // Peel off base 10^9 digits, least significant first
+        let mut n = self.clone();
+        let mut chunks = Vec::new();
+        while !n.limbs.is_empty() {
+            chunks.push(n.divmod_small(1_000_000_000));
+        }
+        let mut chunks = chunks.into_iter().rev();
+        write!(f, "{}", chunks.next().unwrap())?;
+        for chunk in chunks {
+            write!(f, "{:09}", chunk)?;
+        }
+        Ok(())
+    }
+}
+
+impl Count for BigUint {
+    fn zero() -> Self {
+        BigUint { limbs: vec![] }
+    }
+
+    fn from_u64(n: u64) -> Self {
+        BigUint {
+            limbs: vec![n as u32, (n >> 32) as u32],
+        }
+        .trim()
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        let (long, short) = if self.limbs.len() >= other.limbs.len() {
+            (&self.limbs, &other.limbs)
+        } else {
+            (&other.limbs, &self.limbs)
+        };
+        let mut limbs = Vec::with_capacity(long.len() + 1);
+        let mut carry = 0u64;
+        for (i, &l) in long.iter().enumerate() {
+            let sum = l as u64 + short.get(i).copied().unwrap_or(0) as u64 + carry;
+            limbs.push(sum as u32);
+            carry = sum >> 32;
+        }
+        if carry > 0 {
+            limbs.push(carry as u32);
+        }
+        BigUint { limbs }
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        if self.limbs.is_empty() || other.limbs.is_empty() {
+            return Self::zero();
+        }
+        let mut limbs = vec![0u32; self.limbs.len() + other.limbs.len()];
+        for (i, &a) in self.limbs.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, &b) in other.limbs.iter().enumerate() {
+                let cur = limbs[i + j] as u64 + a as u64 * b as u64 + carry;
+                limbs[i + j] = cur as u32;
+                carry = cur >> 32;
+            }
+            limbs[i + other.limbs.len()] = carry as u32;
+        }
+        BigUint { limbs }.trim()
+    }
+}
+
+/// A square matrix of counts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Matrix<C> {
+    size: usize,
+    cells: Vec<C>,
+}
+
+impl<C: Count> Matrix<C> {
+    pub fn zero(size: usize) -> Self {
+        Matrix {
+            size,
+            cells: vec![C::zero(); size * size],
+        }
+    }
+
+    pub fn identity(size: usize) -> Self {
+        let mut m = Self::zero(size);
+        for i in 0..size {
+            m.cells[i * size + i] = C::one();
+        }
+        m
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> &C {
+        &self.cells[row * self.size + col]
+    }
+
+    pub fn set(&mut self, row: usize, col: usize, value: C) {
+        self.cells[row * self.size + col] = value;
+    }
+
+    pub fn mul(&self, other: &Self) -> Self {
+        assert_eq!(self.size, other.size);
+        let n = self.size;
+        let mut out = Self::zero(n);
+        for i in 0..n {
+            for k in 0..n {
+                let a = &self.cells[i * n + k];
+                for j in 0..n {
+                    let b = &other.cells[k * n + j];
+                    out.cells[i * n + j] = out.cells[i * n + j].add(&a.mul(b));
+                }
+            }
+        }
+        out
+    }
+
+    /// The matrix-vector product `self * v`.
+    pub fn apply(&self, v: &[C]) -> Vec<C> {
+        assert_eq!(self.size, v.len());
+        self.cells
+            .chunks(self.size)
+            .map(|row| {
+                row.iter()
+                    .zip(v)
+                    .fold(C::zero(), |acc, (a, b)| acc.add(&a.mul(b)))
+            })
+            .collect()
+    }
+
+    /// `self` raised to the power `exp`, by repeated squaring.
+    pub fn pow(&self, mut exp: u64) -> Self {
+        let mut result = Self::identity(self.size);
+        let mut base = self.clone();
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = result.mul(&base);
+            }
+            exp >>= 1;
+            if exp > 0 {
+                base = base.mul(&base);
+            }
+        }
+        result
+    }
+
+    /// `self^exp * v`, applying each needed square straight to the vector so
+    /// that only the squarings cost a full matrix product.
+    pub fn pow_apply(&self, mut exp: u64, v: &[C]) -> Vec<C> {
+        let mut v = v.to_vec();
+        let mut base = self.clone();
+        while exp > 0 {
+            if exp & 1 == 1 {
+                v = base.apply(&v);
+            }
+            exp >>= 1;
+            if exp > 0 {
+                base = base.mul(&base);
+            }
+        }
+        v
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fib<C: Count>(n: u64) -> C {
+        let mut m = Matrix::<C>::zero(2);
+        m.set(0, 0, C::one());
+        m.set(0, 1, C::one());
+        m.set(1, 0, C::one());
+        m.pow_apply(n, &[C::one(), C::zero()])[1].clone()
+    }
+
+    #[test]
+    fn test_biguint_display() {
+        let n = BigUint::from_u64(u64::MAX);
+        assert_eq!(
+            n.mul(&n).to_string(),
+            "340282366920938463426481119284349108225"
+        );
+        assert_eq!(BigUint::zero().to_string(), "0");
+        assert_eq!(BigUint::from_u64(1_000_000_000).to_string(), "1000000000");
+    }
+
+    #[test]
+    fn test_biguint_order() {
+        let big = BigUint::from_u64(u64::MAX).mul(&BigUint::from_u64(3));
+        assert!(big > BigUint::from_u64(u64::MAX));
+        assert!(BigUint::from_u64(1 << 32) > BigUint::from_u64(u32::MAX as u64));
+        assert!(BigUint::from_u64(5) < BigUint::from_u64(6));
+        assert_eq!(BigUint::zero().cmp(&BigUint::from_u64(0)), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_fibonacci_counts() {
+        assert_eq!(fib::<u64>(90), 2880067194370816120);
+        assert_eq!(
+            fib::<BigUint>(200).to_string(),
+            "280571172992510140037611932413038677189525"
+        );
+        assert_eq!(fib::<Mod<1_000_000_007>>(200).0, 349361645);
+        assert_eq!(
+            Matrix::<u64>::identity(3).pow(5),
+            Matrix::<u64>::identity(3)
+        );
+    }
+}